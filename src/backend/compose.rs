@@ -0,0 +1,331 @@
+//! Rendering of provisioned services as a docker-compose project, so the
+//! environment can be reproduced without ajj.
+
+use super::docker::{container_labels, named_volume};
+use super::traits::{HealthcheckSpec, ResourceOwner, ServiceSpec};
+use crate::error::{AetherError, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+#[derive(Debug, Serialize)]
+struct ComposeFile {
+    name: String,
+    services: BTreeMap<String, ComposeService>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    volumes: BTreeMap<String, ComposeVolume>,
+}
+
+/// A named volume, created by compose under the project's name.
+#[derive(Debug, Serialize)]
+struct ComposeVolume {}
+
+#[derive(Debug, Serialize)]
+struct ComposeService {
+    image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ports: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    environment: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    volumes: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    depends_on: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    healthcheck: Option<ComposeHealthcheck>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deploy: Option<ComposeDeploy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entrypoint: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    working_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hostname: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extra_hosts: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tmpfs: Vec<String>,
+    /// In bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shm_size: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    ulimits: BTreeMap<String, ComposeUlimit>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cap_add: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cap_drop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restart: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_grace_period: Option<String>,
+    labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ComposeUlimit {
+    soft: i64,
+    hard: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct ComposeHealthcheck {
+    test: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interval: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retries: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_period: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ComposeDeploy {
+    resources: ComposeResources,
+}
+
+#[derive(Debug, Serialize)]
+struct ComposeResources {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limits: Option<ComposeLimits>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reservations: Option<ComposeLimits>,
+}
+
+#[derive(Debug, Serialize)]
+struct ComposeLimits {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpus: Option<f64>,
+    /// In bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory: Option<String>,
+}
+
+impl ComposeLimits {
+    fn new(cpus: Option<f64>, memory: Option<i64>) -> Option<Self> {
+        (cpus.is_some() || memory.is_some()).then(|| ComposeLimits {
+            cpus,
+            memory: memory.map(|m| m.to_string()),
+        })
+    }
+}
+
+/// Render `services` as the compose project `namespace`, with the same
+/// host ports and labels as [`DockerBackend::provision`](super::DockerBackend)
+/// gives them. The network and named volumes are compose's own rather than
+/// ajj's, so the project starts whether or not ajj's environment exists.
+/// Bind sources are expected to be absolute already.
+pub fn render_compose(
+    namespace: &str,
+    owner: &ResourceOwner,
+    services: &HashMap<String, ServiceSpec>,
+) -> Result<String> {
+    let mut volumes = BTreeMap::new();
+    let services = services
+        .iter()
+        .map(|(name, spec)| {
+            let mut ports: Vec<String> = spec
+                .ports
+                .iter()
+                .map(|port| match spec.port_mappings.get(port) {
+                    Some(host) => format!("{}:{}", host, port),
+                    None => port.to_string(),
+                })
+                .collect();
+            ports.sort_by_key(|p| p.rsplit(':').next().and_then(|c| c.parse::<u16>().ok()));
+
+            let service_volumes = spec
+                .volumes
+                .iter()
+                .map(|volume| {
+                    if let Some(named) = named_volume(volume) {
+                        volumes.insert(named.to_string(), ComposeVolume {});
+                    }
+                    escape(volume)
+                })
+                .collect();
+
+            let resources = ComposeResources {
+                limits: ComposeLimits::new(spec.cpu_limit, spec.memory_limit),
+                reservations: ComposeLimits::new(spec.cpu_reservation, spec.memory_reservation),
+            };
+            let deploy = (resources.limits.is_some() || resources.reservations.is_some())
+                .then_some(ComposeDeploy { resources });
+
+            // The service's own labels, with the Aether ones taking precedence
+            let mut labels = spec.labels.clone();
+            labels.extend(container_labels(namespace, owner, name));
+            let escape_all =
+                |items: &[String]| -> Vec<String> { items.iter().map(|i| escape(i)).collect() };
+
+            let service = ComposeService {
+                image: escape(&spec.image),
+                command: spec.command.as_deref().map(escape_all),
+                ports,
+                environment: escape_map(spec.env.iter()),
+                volumes: service_volumes,
+                depends_on: spec.depends_on.clone(),
+                healthcheck: spec.healthcheck.as_ref().map(healthcheck),
+                deploy,
+                entrypoint: spec.entrypoint.as_deref().map(escape_all),
+                working_dir: spec.working_dir.as_deref().map(escape),
+                user: spec.user.as_deref().map(escape),
+                hostname: spec.hostname.as_deref().map(escape),
+                extra_hosts: escape_all(&spec.extra_hosts),
+                tmpfs: escape_all(&spec.tmpfs),
+                shm_size: spec.shm_size.map(|bytes| bytes.to_string()),
+                ulimits: spec
+                    .ulimits
+                    .iter()
+                    .map(|(name, &(soft, hard))| (name.clone(), ComposeUlimit { soft, hard }))
+                    .collect(),
+                cap_add: spec.cap_add.clone(),
+                cap_drop: spec.cap_drop.clone(),
+                restart: spec
+                    .restart
+                    .as_ref()
+                    .map(|restart| match restart.max_retries {
+                        Some(retries) => format!("{}:{}", restart.policy, retries),
+                        None => restart.policy.clone(),
+                    }),
+                stop_grace_period: spec.stop_grace_period.map(duration),
+                labels: escape_map(labels.iter()),
+            };
+            (name.clone(), service)
+        })
+        .collect();
+
+    let file = ComposeFile {
+        name: namespace.to_string(),
+        services,
+        volumes,
+    };
+    serde_yaml::to_string(&file)
+        .map_err(|e| AetherError::Backend(format!("Failed to render compose file: {}", e)))
+}
+
+fn healthcheck(healthcheck: &HealthcheckSpec) -> ComposeHealthcheck {
+    ComposeHealthcheck {
+        test: healthcheck.test.iter().map(|arg| escape(arg)).collect(),
+        interval: healthcheck.interval.map(duration),
+        timeout: healthcheck.timeout.map(duration),
+        retries: healthcheck.retries,
+        start_period: healthcheck.start_period.map(duration),
+    }
+}
+
+/// A compose duration such as `5s` or `1500ms`.
+fn duration(duration: Duration) -> String {
+    if duration.subsec_millis() == 0 {
+        format!("{}s", duration.as_secs())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+/// Escape `$` so compose does not interpolate values that are already
+/// resolved.
+fn escape(value: &str) -> String {
+    value.replace('$', "$$")
+}
+
+fn escape_map<'a>(
+    entries: impl Iterator<Item = (&'a String, &'a String)>,
+) -> BTreeMap<String, String> {
+    entries.map(|(k, v)| (k.clone(), escape(v))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RestartSpec;
+
+    #[test]
+    fn test_render_compose() {
+        let owner = ResourceOwner {
+            repo_id: "abc".to_string(),
+            workspace_name: "feature".to_string(),
+            workspace_path: "/src/feature".to_string(),
+        };
+        let spec = ServiceSpec {
+            name: "postgres".to_string(),
+            image: "postgres:16".to_string(),
+            ports: vec![5432],
+            env: HashMap::from([("POSTGRES_PASSWORD".to_string(), "pa$$".to_string())]),
+            volumes: vec![
+                "pgdata:/var/lib/postgresql/data".to_string(),
+                "/src/feature/init:/docker-entrypoint-initdb.d:ro".to_string(),
+            ],
+            command: None,
+            port_mappings: HashMap::from([(5432, 54321)]),
+            depends_on: Vec::new(),
+            cpu_limit: Some(0.5),
+            cpu_reservation: None,
+            memory_limit: Some(536870912),
+            memory_reservation: None,
+            healthcheck: Some(HealthcheckSpec {
+                test: vec!["CMD".to_string(), "pg_isready".to_string()],
+                interval: Some(Duration::from_secs(5)),
+                timeout: Some(Duration::from_millis(1500)),
+                retries: Some(10),
+                start_period: None,
+            }),
+            entrypoint: None,
+            working_dir: None,
+            user: Some("postgres".to_string()),
+            hostname: None,
+            extra_hosts: Vec::new(),
+            tmpfs: vec!["/run/postgresql".to_string()],
+            shm_size: Some(268435456),
+            ulimits: HashMap::from([("nofile".to_string(), (1024, 65536))]),
+            cap_add: Vec::new(),
+            cap_drop: vec!["ALL".to_string()],
+            labels: HashMap::from([
+                ("team".to_string(), "data".to_string()),
+                ("aether.service".to_string(), "spoofed".to_string()),
+            ]),
+            restart: Some(RestartSpec {
+                policy: "on-failure".to_string(),
+                max_retries: Some(3),
+            }),
+            stop_grace_period: Some(Duration::from_secs(30)),
+        };
+        let services = HashMap::from([("postgres".to_string(), spec)]);
+
+        let rendered = render_compose("aether-abc-feature", &owner, &services).unwrap();
+        let document: serde_yaml::Value = serde_yaml::from_str(&rendered).unwrap();
+        let service = &document["services"]["postgres"];
+        assert_eq!(document["name"], "aether-abc-feature");
+        // Compose creates its own network and volumes, not ajj's
+        assert!(document["networks"].is_null());
+        assert!(document["volumes"]["pgdata"].is_mapping());
+        assert_eq!(document["volumes"].as_mapping().unwrap().len(), 1);
+        assert_eq!(service["ports"][0], "54321:5432");
+        assert_eq!(service["environment"]["POSTGRES_PASSWORD"], "pa$$$$");
+        assert_eq!(service["volumes"][0], "pgdata:/var/lib/postgresql/data");
+        assert_eq!(
+            service["volumes"][1],
+            "/src/feature/init:/docker-entrypoint-initdb.d:ro"
+        );
+        assert_eq!(service["healthcheck"]["interval"], "5s");
+        assert_eq!(service["healthcheck"]["timeout"], "1500ms");
+        assert_eq!(service["deploy"]["resources"]["limits"]["cpus"], 0.5);
+        assert_eq!(
+            service["deploy"]["resources"]["limits"]["memory"],
+            "536870912"
+        );
+        assert!(service["deploy"]["resources"]["reservations"].is_null());
+        assert_eq!(service["shm_size"], "268435456");
+        assert_eq!(service["ulimits"]["nofile"]["hard"], 65536);
+        assert_eq!(service["restart"], "on-failure:3");
+        assert_eq!(service["stop_grace_period"], "30s");
+        assert_eq!(service["labels"]["team"], "data");
+        assert_eq!(service["labels"]["aether.service"], "postgres");
+        assert_eq!(service["labels"]["aether.workspace_name"], "feature");
+    }
+}
@@ -1,4 +1,7 @@
-use super::traits::{Backend, ContainerExecResult, ResourceHandle, ResourceStatus, ServiceSpec};
+use super::traits::{
+    Backend, ContainerExecResult, HealthcheckSpec, ManagedResource, ResourceHandle, ResourceKind,
+    ResourceOwner, ResourceStatus, ServiceSpec,
+};
 use crate::error::{AetherError, Result};
 use async_trait::async_trait;
 use bollard::container::{
@@ -6,12 +9,111 @@ use bollard::container::{
     RestartContainerOptions, StartContainerOptions, StopContainerOptions,
 };
 use bollard::exec::{CreateExecOptions, StartExecResults};
-use bollard::models::{EndpointSettings, HostConfig, PortBinding};
+use bollard::models::{
+    EndpointSettings, HealthConfig, HostConfig, PortBinding, ResourcesUlimits, RestartPolicy,
+};
 use bollard::network::{CreateNetworkOptions, ListNetworksOptions};
+use bollard::volume::{CreateVolumeOptions, ListVolumesOptions, RemoveVolumeOptions};
 use bollard::Docker;
 use futures_util::StreamExt;
 use std::collections::HashMap;
 
+/// Label carrying the repo hash of the repository that owns a resource.
+pub const REPO_LABEL: &str = "aether.repo";
+/// Labels recording which workspace a container was provisioned for.
+pub const WORKSPACE_NAME_LABEL: &str = "aether.workspace_name";
+pub const WORKSPACE_PATH_LABEL: &str = "aether.workspace_path";
+
+/// Name of the network a workspace's containers share.
+pub fn network_name(namespace: &str) -> String {
+    format!("{}-network", namespace)
+}
+
+/// Labels attributing the container of `service` to its namespace and
+/// owning workspace.
+pub fn container_labels(
+    namespace: &str,
+    owner: &ResourceOwner,
+    service: &str,
+) -> HashMap<String, String> {
+    let mut labels = HashMap::new();
+    labels.insert("aether.managed".to_string(), "true".to_string());
+    labels.insert("aether.workspace".to_string(), namespace.to_string());
+    labels.insert("aether.namespace".to_string(), namespace.to_string());
+    labels.insert("aether.service".to_string(), service.to_string());
+    labels.insert(REPO_LABEL.to_string(), owner.repo_id.clone());
+    labels.insert(
+        WORKSPACE_NAME_LABEL.to_string(),
+        owner.workspace_name.clone(),
+    );
+    labels.insert(
+        WORKSPACE_PATH_LABEL.to_string(),
+        owner.workspace_path.clone(),
+    );
+    labels
+}
+
+/// Docker's healthcheck settings, with durations in nanoseconds.
+fn health_config(healthcheck: &HealthcheckSpec) -> HealthConfig {
+    let nanos = |d: Option<std::time::Duration>| d.map(|d| d.as_nanos() as i64);
+    HealthConfig {
+        test: Some(healthcheck.test.clone()),
+        interval: nanos(healthcheck.interval),
+        timeout: nanos(healthcheck.timeout),
+        retries: healthcheck.retries.map(i64::from),
+        start_period: nanos(healthcheck.start_period),
+        ..Default::default()
+    }
+}
+
+/// Source of a `source:target[:mode]` spec when it names a volume rather
+/// than a bind mount (absolute, relative or home path).
+pub(crate) fn named_volume(spec: &str) -> Option<&str> {
+    let (source, _) = spec.split_once(':')?;
+    let bind = source.is_empty()
+        || source.starts_with('/')
+        || source.starts_with('.')
+        || source.starts_with('~');
+    (!bind).then_some(source)
+}
+
+/// Scope named volumes in a `source:target[:mode]` spec to the namespace:
+/// `pgdata` becomes `<namespace>-pgdata`, so every workspace has its own data.
+/// Such volumes outlive [`Backend::deprovision`] and are only removed by
+/// [`Backend::remove_volumes`]. Provisioning keeps using an existing volume
+/// of the unscoped name, which workspaces mounted before this scoping.
+///
+/// Returns the rewritten spec and, for named volumes, the volume to create.
+/// Bind mounts pass through unchanged.
+pub(crate) fn scope_volume(namespace: &str, spec: &str) -> (String, Option<String>) {
+    match named_volume(spec) {
+        Some(source) => {
+            let volume = format!("{}-{}", namespace, source);
+            (format!("{}{}", volume, &spec[source.len()..]), Some(volume))
+        }
+        None => (spec.to_string(), None),
+    }
+}
+
+fn managed_from_labels(
+    kind: ResourceKind,
+    id: String,
+    name: String,
+    labels: &HashMap<String, String>,
+) -> ManagedResource {
+    ManagedResource {
+        kind,
+        id,
+        name,
+        namespace: labels.get("aether.namespace").cloned(),
+        service: labels.get("aether.service").cloned(),
+        repo: labels.get(REPO_LABEL).cloned(),
+        workspace_name: labels.get(WORKSPACE_NAME_LABEL).cloned(),
+        workspace_path: labels.get(WORKSPACE_PATH_LABEL).cloned(),
+        image: None,
+    }
+}
+
 pub struct DockerBackend {
     client: Docker,
 }
@@ -25,7 +127,12 @@ impl DockerBackend {
     }
 
     /// Ensure the network exists for this workspace
-    async fn ensure_network(&self, network_name: &str, namespace: &str) -> Result<()> {
+    async fn ensure_network(
+        &self,
+        network_name: &str,
+        namespace: &str,
+        repo_id: &str,
+    ) -> Result<()> {
         // Check if network already exists
         let mut filters = HashMap::new();
         filters.insert("name".to_string(), vec![network_name.to_string()]);
@@ -41,6 +148,7 @@ impl DockerBackend {
             let mut labels = HashMap::new();
             labels.insert("aether.managed".to_string(), "true".to_string());
             labels.insert("aether.namespace".to_string(), namespace.to_string());
+            labels.insert(REPO_LABEL.to_string(), repo_id.to_string());
 
             self.client
                 .create_network(CreateNetworkOptions {
@@ -58,7 +166,7 @@ impl DockerBackend {
 
     /// Remove the network for this workspace
     async fn remove_network(&self, namespace: &str) -> Result<()> {
-        let network_name = format!("{}-network", namespace);
+        let network_name = network_name(namespace);
 
         // Try to remove the network, ignoring errors if it doesn't exist
         let _ = self.client.remove_network(&network_name).await;
@@ -66,6 +174,48 @@ impl DockerBackend {
         Ok(())
     }
 
+    /// Bind for a `source:target[:mode]` volume spec, creating the workspace's
+    /// scoped named volume. A volume of the unscoped name that predates
+    /// per-workspace volumes is mounted as before instead, so its data is not
+    /// left behind on an empty new volume.
+    async fn bind_volume(&self, spec: &str, namespace: &str, repo_id: &str) -> Result<String> {
+        let (bind, volume) = scope_volume(namespace, spec);
+        let (Some(volume_name), Some(source)) = (volume, named_volume(spec)) else {
+            return Ok(bind);
+        };
+
+        let exists = |name: &str| {
+            let name = name.to_string();
+            async move { self.client.inspect_volume(&name).await.is_ok() }
+        };
+        if !exists(&volume_name).await && exists(source).await {
+            return Ok(spec.to_string());
+        }
+
+        self.ensure_volume(&volume_name, namespace, repo_id).await?;
+        Ok(bind)
+    }
+
+    /// Create a labelled named volume for this workspace
+    async fn ensure_volume(&self, volume_name: &str, namespace: &str, repo_id: &str) -> Result<()> {
+        let mut labels = HashMap::new();
+        labels.insert("aether.managed".to_string(), "true".to_string());
+        labels.insert("aether.namespace".to_string(), namespace.to_string());
+        labels.insert(REPO_LABEL.to_string(), repo_id.to_string());
+
+        // Creating a volume that already exists is a no-op in Docker
+        self.client
+            .create_volume(CreateVolumeOptions {
+                name: volume_name.to_string(),
+                labels,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| AetherError::Backend(format!("Failed to create volume: {}", e)))?;
+
+        Ok(())
+    }
+
     /// Find a container by namespace and service name
     async fn find_container(&self, namespace: &str, service: &str) -> Result<String> {
         let mut filters = HashMap::new();
@@ -104,13 +254,15 @@ impl Backend for DockerBackend {
     async fn provision(
         &self,
         namespace: &str,
+        owner: &ResourceOwner,
         services: &HashMap<String, ServiceSpec>,
     ) -> Result<Vec<ResourceHandle>> {
         let mut handles = Vec::new();
-        let network_name = format!("{}-network", namespace);
+        let network_name = network_name(namespace);
 
         // Create dedicated network for this workspace
-        self.ensure_network(&network_name, namespace).await?;
+        self.ensure_network(&network_name, namespace, &owner.repo_id)
+            .await?;
 
         for (name, spec) in services {
             let container_name = format!("{}-{}", namespace, name);
@@ -128,12 +280,9 @@ impl Backend for DockerBackend {
                 );
             }
 
-            // Build labels
-            let mut labels = HashMap::new();
-            labels.insert("aether.managed".to_string(), "true".to_string());
-            labels.insert("aether.workspace".to_string(), namespace.to_string());
-            labels.insert("aether.namespace".to_string(), namespace.to_string());
-            labels.insert("aether.service".to_string(), name.clone());
+            // The service's own labels, with the Aether ones taking precedence
+            let mut labels = spec.labels.clone();
+            labels.extend(container_labels(namespace, owner, name));
 
             // Build env vars
             let env: Vec<String> = spec
@@ -148,8 +297,14 @@ impl Backend for DockerBackend {
                 exposed_ports.insert(format!("{}/tcp", internal), HashMap::new());
             }
 
-            // Build volume bindings
-            let binds: Vec<String> = spec.volumes.clone();
+            // Build volume bindings, creating namespaced named volumes
+            let mut binds = Vec::new();
+            for volume_spec in &spec.volumes {
+                binds.push(
+                    self.bind_volume(volume_spec, namespace, &owner.repo_id)
+                        .await?,
+                );
+            }
 
             // Network configuration - use service name as network alias
             let mut endpoints_config = HashMap::new();
@@ -168,6 +323,30 @@ impl Backend for DockerBackend {
             let memory = spec.memory_limit;
             let memory_reservation = spec.memory_reservation;
 
+            // Build the remaining host options
+            let non_empty = |items: &Vec<String>| (!items.is_empty()).then(|| items.clone());
+            let tmpfs: HashMap<String, String> = spec
+                .tmpfs
+                .iter()
+                .map(|mount| match mount.split_once(':') {
+                    Some((path, options)) => (path.to_string(), options.to_string()),
+                    None => (mount.clone(), String::new()),
+                })
+                .collect();
+            let ulimits: Vec<ResourcesUlimits> = spec
+                .ulimits
+                .iter()
+                .map(|(name, (soft, hard))| ResourcesUlimits {
+                    name: Some(name.clone()),
+                    soft: Some(*soft),
+                    hard: Some(*hard),
+                })
+                .collect();
+            let restart_policy = spec.restart.as_ref().map(|restart| RestartPolicy {
+                name: restart.policy.parse().ok(),
+                maximum_retry_count: restart.max_retries.map(i64::from),
+            });
+
             // Create container config
             let config = Config {
                 image: Some(spec.image.clone()),
@@ -183,10 +362,26 @@ impl Backend for DockerBackend {
                     cpu_shares,
                     memory,
                     memory_reservation,
+                    extra_hosts: non_empty(&spec.extra_hosts),
+                    tmpfs: (!tmpfs.is_empty()).then_some(tmpfs),
+                    shm_size: spec.shm_size,
+                    ulimits: (!ulimits.is_empty()).then_some(ulimits),
+                    cap_add: non_empty(&spec.cap_add),
+                    cap_drop: non_empty(&spec.cap_drop),
+                    restart_policy,
                     ..Default::default()
                 }),
                 networking_config: Some(bollard::container::NetworkingConfig { endpoints_config }),
                 cmd: spec.command.clone(),
+                healthcheck: spec.healthcheck.as_ref().map(health_config),
+                entrypoint: spec.entrypoint.clone(),
+                working_dir: spec.working_dir.clone(),
+                user: spec.user.clone(),
+                hostname: spec.hostname.clone(),
+                // Docker takes whole seconds; round up so the grace period is never shortened
+                stop_timeout: spec
+                    .stop_grace_period
+                    .map(|d| d.as_secs_f64().ceil() as i64),
                 ..Default::default()
             };
 
@@ -256,12 +451,36 @@ impl Backend for DockerBackend {
             }
         }
 
-        // Remove the network after all containers are gone
+        // Remove the network after all containers are gone; named volumes
+        // keep the workspace's data until it is forgotten
         self.remove_network(namespace).await?;
 
         Ok(())
     }
 
+    async fn remove_volumes(&self, namespace: &str) -> Result<()> {
+        let mut filters = HashMap::new();
+        filters.insert(
+            "label".to_string(),
+            vec![format!("aether.namespace={}", namespace)],
+        );
+
+        let volumes = self
+            .client
+            .list_volumes(Some(ListVolumesOptions { filters }))
+            .await
+            .map_err(|e| AetherError::Backend(format!("Failed to list volumes: {}", e)))?;
+
+        for volume in volumes.volumes.unwrap_or_default() {
+            self.client
+                .remove_volume(&volume.name, Some(RemoveVolumeOptions { force: true }))
+                .await
+                .map_err(|e| AetherError::Backend(format!("Failed to remove volume: {}", e)))?;
+        }
+
+        Ok(())
+    }
+
     async fn status(&self, namespace: &str) -> Result<Vec<ResourceStatus>> {
         let mut filters = HashMap::new();
         filters.insert(
@@ -436,6 +655,129 @@ impl Backend for DockerBackend {
         })
     }
 
+    async fn list_managed(&self) -> Result<Vec<ManagedResource>> {
+        let mut filters = HashMap::new();
+        filters.insert("label".to_string(), vec!["aether.managed=true".to_string()]);
+
+        let mut resources = Vec::new();
+
+        let containers = self
+            .client
+            .list_containers(Some(ListContainersOptions {
+                all: true,
+                filters: filters.clone(),
+                ..Default::default()
+            }))
+            .await
+            .map_err(|e| AetherError::Backend(format!("Failed to list containers: {}", e)))?;
+
+        for container in containers {
+            let id = container.id.unwrap_or_default();
+            let name = container
+                .names
+                .and_then(|names| names.first().cloned())
+                .map(|n| n.trim_start_matches('/').to_string())
+                .unwrap_or_else(|| id.clone());
+            let mut resource = managed_from_labels(
+                ResourceKind::Container,
+                id,
+                name,
+                &container.labels.unwrap_or_default(),
+            );
+            resource.image = container.image;
+            resources.push(resource);
+        }
+
+        let networks = self
+            .client
+            .list_networks(Some(ListNetworksOptions {
+                filters: filters.clone(),
+            }))
+            .await
+            .map_err(|e| AetherError::Backend(format!("Failed to list networks: {}", e)))?;
+
+        for network in networks {
+            let name = network.name.unwrap_or_default();
+            resources.push(managed_from_labels(
+                ResourceKind::Network,
+                network.id.unwrap_or_else(|| name.clone()),
+                name,
+                &network.labels.unwrap_or_default(),
+            ));
+        }
+
+        let volumes = self
+            .client
+            .list_volumes(Some(ListVolumesOptions { filters }))
+            .await
+            .map_err(|e| AetherError::Backend(format!("Failed to list volumes: {}", e)))?;
+
+        for volume in volumes.volumes.unwrap_or_default() {
+            resources.push(managed_from_labels(
+                ResourceKind::Volume,
+                volume.name.clone(),
+                volume.name,
+                &volume.labels,
+            ));
+        }
+
+        Ok(resources)
+    }
+
+    async fn inspect_ports(&self, container_id: &str) -> Result<HashMap<u16, u16>> {
+        let info = self
+            .client
+            .inspect_container(container_id, None)
+            .await
+            .map_err(|e| AetherError::Backend(format!("Failed to inspect container: {}", e)))?;
+
+        let mut port_mappings = HashMap::new();
+        let bindings = info
+            .host_config
+            .and_then(|h| h.port_bindings)
+            .unwrap_or_default();
+
+        for (port_key, host_bindings) in bindings {
+            let internal = port_key.split('/').next().and_then(|p| p.parse().ok());
+            let external = host_bindings
+                .unwrap_or_default()
+                .into_iter()
+                .find_map(|b| b.host_port.and_then(|p| p.parse().ok()));
+
+            if let (Some(internal), Some(external)) = (internal, external) {
+                port_mappings.insert(internal, external);
+            }
+        }
+
+        Ok(port_mappings)
+    }
+
+    async fn remove_managed(&self, resource: &ManagedResource) -> Result<()> {
+        match resource.kind {
+            ResourceKind::Container => self
+                .client
+                .remove_container(
+                    &resource.id,
+                    Some(RemoveContainerOptions {
+                        force: true,
+                        ..Default::default()
+                    }),
+                )
+                .await
+                .map_err(|e| AetherError::Backend(format!("Failed to remove container: {}", e))),
+            ResourceKind::Network => self
+                .client
+                .remove_network(&resource.id)
+                .await
+                .map_err(|e| AetherError::Backend(format!("Failed to remove network: {}", e))),
+            ResourceKind::Volume => self
+                .client
+                .remove_volume(&resource.id, Some(RemoveVolumeOptions { force: true }))
+                .await
+                .map_err(|e| AetherError::Backend(format!("Failed to remove volume: {}", e))),
+        }
+    }
+
     fn backend_type(&self) -> &'static str {
         "docker"
     }
@@ -445,6 +787,31 @@ impl Backend for DockerBackend {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_scope_volume() {
+        assert_eq!(
+            scope_volume("aether-abc-ws", "pgdata:/var/lib/postgresql/data"),
+            (
+                "aether-abc-ws-pgdata:/var/lib/postgresql/data".to_string(),
+                Some("aether-abc-ws-pgdata".to_string())
+            )
+        );
+        assert_eq!(
+            scope_volume(
+                "aether-abc-ws",
+                "./init.sql:/docker-entrypoint-initdb.d/init.sql:ro"
+            ),
+            (
+                "./init.sql:/docker-entrypoint-initdb.d/init.sql:ro".to_string(),
+                None
+            )
+        );
+        assert_eq!(
+            scope_volume("aether-abc-ws", "/var/lib/data"),
+            ("/var/lib/data".to_string(), None)
+        );
+    }
+
     #[tokio::test]
     async fn test_docker_backend_creation() {
         // This test requires Docker to be running
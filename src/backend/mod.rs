@@ -1,5 +1,7 @@
+pub mod compose;
 pub mod docker;
 pub mod traits;
 
+pub use compose::*;
 pub use docker::*;
 pub use traits::*;
@@ -1,17 +1,27 @@
 use crate::error::Result;
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::time::Duration;
 
 #[async_trait]
 pub trait Backend: Send + Sync {
+    /// Create every service under `namespace`. Resources are labelled with
+    /// `owner` so they can be attributed to their workspace later.
     async fn provision(
         &self,
         namespace: &str,
+        owner: &ResourceOwner,
         services: &HashMap<String, ServiceSpec>,
     ) -> Result<Vec<ResourceHandle>>;
 
+    /// Remove the containers and network of `namespace`. Named volumes are
+    /// kept, so detaching or undoing does not lose data.
     async fn deprovision(&self, namespace: &str) -> Result<()>;
 
+    /// Remove the named volumes of `namespace`, when its workspace is
+    /// forgotten or cleaned up for good.
+    async fn remove_volumes(&self, namespace: &str) -> Result<()>;
+
     async fn status(&self, namespace: &str) -> Result<Vec<ResourceStatus>>;
 
     async fn logs(&self, namespace: &str, service: &str, tail: Option<usize>) -> Result<String>;
@@ -29,9 +39,49 @@ pub trait Backend: Send + Sync {
         command: &[String],
     ) -> Result<ContainerExecResult>;
 
+    /// List every resource carrying the `aether.managed` label, across all
+    /// namespaces and repositories.
+    async fn list_managed(&self) -> Result<Vec<ManagedResource>>;
+
+    /// Host port bindings of a container, read from its configuration so they
+    /// are available even while it is stopped.
+    async fn inspect_ports(&self, container_id: &str) -> Result<HashMap<u16, u16>>;
+
+    /// Force-remove a resource returned by [`Backend::list_managed`].
+    async fn remove_managed(&self, resource: &ManagedResource) -> Result<()>;
+
     fn backend_type(&self) -> &'static str;
 }
 
+/// The workspace a set of resources is provisioned for.
+#[derive(Debug, Clone)]
+pub struct ResourceOwner {
+    pub repo_id: String,
+    pub workspace_name: String,
+    pub workspace_path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    Container,
+    Network,
+    Volume,
+}
+
+/// A backend resource identified by its Aether labels rather than by state.
+#[derive(Debug, Clone)]
+pub struct ManagedResource {
+    pub kind: ResourceKind,
+    pub id: String,
+    pub name: String,
+    pub namespace: Option<String>,
+    pub service: Option<String>,
+    pub repo: Option<String>,
+    pub workspace_name: Option<String>,
+    pub workspace_path: Option<String>,
+    pub image: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ServiceSpec {
     pub name: String,
@@ -46,6 +96,39 @@ pub struct ServiceSpec {
     pub cpu_reservation: Option<f64>,
     pub memory_limit: Option<i64>,
     pub memory_reservation: Option<i64>,
+    pub healthcheck: Option<HealthcheckSpec>,
+    pub entrypoint: Option<Vec<String>>,
+    pub working_dir: Option<String>,
+    pub user: Option<String>,
+    pub hostname: Option<String>,
+    pub extra_hosts: Vec<String>,
+    pub tmpfs: Vec<String>,
+    pub shm_size: Option<i64>,
+    pub ulimits: HashMap<String, (i64, i64)>, // name -> (soft, hard)
+    pub cap_add: Vec<String>,
+    pub cap_drop: Vec<String>,
+    pub labels: HashMap<String, String>,
+    pub restart: Option<RestartSpec>,
+    pub stop_grace_period: Option<Duration>,
+}
+
+/// What to do when a container exits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestartSpec {
+    /// `no`, `always`, `unless-stopped` or `on-failure`
+    pub policy: String,
+    /// Only for `on-failure`
+    pub max_retries: Option<u32>,
+}
+
+/// A container healthcheck with its durations parsed.
+#[derive(Debug, Clone)]
+pub struct HealthcheckSpec {
+    pub test: Vec<String>,
+    pub interval: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub retries: Option<u32>,
+    pub start_period: Option<Duration>,
 }
 
 #[derive(Debug, Clone)]
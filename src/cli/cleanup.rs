@@ -0,0 +1,328 @@
+use crate::backend::{Backend, DockerBackend, ManagedResource, ResourceKind};
+use crate::error::Result;
+use crate::output::json::{CleanupOutput, OrphanReport, StaleWorkspaceReport};
+use crate::provisioner::{OperationKind, RepoIndex, StateManager, WorkspaceState};
+use crate::repo::find_repo_root;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Why a registry entry no longer matches reality.
+#[derive(Debug, Clone, PartialEq)]
+enum Staleness {
+    /// The workspace directory is gone; the entry should be dropped.
+    PathMissing,
+    /// Some recorded containers are gone; the entry can be repaired.
+    ContainersMissing(Vec<String>),
+}
+
+/// Registry roots to consult: the current repository and, with `all_repos`,
+/// every repository recorded in the user-level index.
+fn registry_roots(repo_root: Option<&Path>, all_repos: bool) -> Result<Vec<PathBuf>> {
+    let mut roots: Vec<PathBuf> = repo_root.map(Path::to_path_buf).into_iter().collect();
+
+    if all_repos {
+        if let Some(index) = RepoIndex::user_default() {
+            for (_, root) in index.repos()? {
+                if !roots.contains(&root) {
+                    roots.push(root);
+                }
+            }
+        }
+    }
+
+    Ok(roots)
+}
+
+/// Resources whose namespace no registry owns, among those labelled with one
+/// of the consulted repositories `repos`. Resources of other or unknown
+/// repositories, and unlabelled ones, are never orphans: their registry was
+/// not read.
+fn find_orphans<'a>(
+    resources: &'a [ManagedResource],
+    registered: &HashSet<String>,
+    repos: &HashSet<String>,
+) -> Vec<&'a ManagedResource> {
+    resources
+        .iter()
+        .filter(|r| r.repo.as_ref().is_some_and(|repo| repos.contains(repo)))
+        .filter(|r| match &r.namespace {
+            Some(namespace) => !registered.contains(namespace),
+            None => false,
+        })
+        .collect()
+}
+
+fn find_staleness(workspace: &WorkspaceState, resources: &[ManagedResource]) -> Option<Staleness> {
+    if !Path::new(&workspace.path).exists() {
+        return Some(Staleness::PathMissing);
+    }
+
+    let live: HashSet<&str> = resources
+        .iter()
+        .filter(|r| r.kind == ResourceKind::Container)
+        .filter(|r| r.namespace.as_deref() == Some(workspace.namespace.as_str()))
+        .filter_map(|r| r.service.as_deref())
+        .collect();
+
+    let missing: Vec<String> = workspace
+        .resources
+        .iter()
+        .filter(|r| !live.contains(r.service_name.as_str()))
+        .map(|r| r.service_name.clone())
+        .collect();
+
+    if missing.is_empty() {
+        None
+    } else {
+        Some(Staleness::ContainersMissing(missing))
+    }
+}
+
+pub async fn handle_cleanup(force: bool, all_repos: bool, json: bool) -> Result<()> {
+    // Outside a repository there is nothing to scope to, so only --all-repos works
+    let repo_root = if all_repos {
+        find_repo_root(Path::new(".")).ok()
+    } else {
+        Some(find_repo_root(Path::new("."))?)
+    };
+
+    let mut registered = HashSet::new();
+    let mut repos = HashSet::new();
+    let mut undoable = HashSet::new();
+    let mut workspaces = Vec::new();
+    for root in registry_roots(repo_root.as_deref(), all_repos)? {
+        let state_manager = StateManager::new(&root);
+        repos.insert(state_manager.repo_hash().to_string());
+        for record in state_manager.list_operations()? {
+            if record.kind == OperationKind::WorkspaceForget {
+                undoable.insert(record.workspace.namespace);
+            }
+        }
+        for ws in state_manager.list_workspaces()? {
+            registered.insert(ws.namespace.clone());
+            workspaces.push((root.clone(), ws));
+        }
+    }
+
+    let backend = DockerBackend::new()?;
+    let resources = backend.list_managed().await?;
+
+    let mut orphans = find_orphans(&resources, &registered, &repos);
+    // Volumes of a forget that `ajj undo` can still reverse hold its data
+    orphans.retain(|r| {
+        r.kind != ResourceKind::Volume
+            || !r.namespace.as_ref().is_some_and(|ns| undoable.contains(ns))
+    });
+    // Containers must go before the networks and volumes they use
+    orphans.sort_by_key(|r| match r.kind {
+        ResourceKind::Container => 0,
+        ResourceKind::Network => 1,
+        ResourceKind::Volume => 2,
+    });
+
+    let stale: Vec<_> = workspaces
+        .into_iter()
+        .filter_map(|(root, ws)| find_staleness(&ws, &resources).map(|s| (root, ws, s)))
+        .collect();
+
+    let mut containers = OrphanReport::default();
+    let mut networks = OrphanReport::default();
+    let mut volumes = OrphanReport::default();
+    let mut removed = Vec::new();
+
+    if !json {
+        if orphans.is_empty() {
+            println!("No orphaned resources found.");
+        } else {
+            println!("Found {} orphaned resource(s):", orphans.len());
+        }
+    }
+
+    for resource in &orphans {
+        let report = match resource.kind {
+            ResourceKind::Container => &mut containers,
+            ResourceKind::Network => &mut networks,
+            ResourceKind::Volume => &mut volumes,
+        };
+        report.orphaned.push(resource.name.clone());
+
+        if !json {
+            println!(
+                "  - {:?} {} ({}/{})",
+                resource.kind,
+                resource.name,
+                resource.namespace.as_deref().unwrap_or("-"),
+                resource.service.as_deref().unwrap_or("-")
+            );
+        }
+
+        if force {
+            backend.remove_managed(resource).await?;
+            report.removed.push(resource.name.clone());
+            removed.push(resource.name.clone());
+            if !json {
+                println!("    Removed: {}", resource.name);
+            }
+        }
+    }
+
+    if !json && !stale.is_empty() {
+        println!("\nFound {} stale workspace entr(ies):", stale.len());
+    }
+
+    let mut stale_workspaces = Vec::new();
+    for (root, ws, staleness) in &stale {
+        let state_manager = StateManager::new(root);
+        let (reason, action, missing_services) = match staleness {
+            Staleness::PathMissing => ("path_missing", "drop", vec![]),
+            Staleness::ContainersMissing(missing) => {
+                ("containers_missing", "repair", missing.clone())
+            }
+        };
+
+        if !json {
+            match staleness {
+                Staleness::PathMissing => {
+                    println!("  - {}: {} no longer exists (drop)", ws.name, ws.path)
+                }
+                Staleness::ContainersMissing(missing) => println!(
+                    "  - {}: containers missing for {} (repair)",
+                    ws.name,
+                    missing.join(", ")
+                ),
+            }
+        }
+
+        if force {
+            match staleness {
+                Staleness::PathMissing => {
+                    backend.deprovision(&ws.namespace).await?;
+                    backend.remove_volumes(&ws.namespace).await?;
+                    state_manager.unregister_workspace(&ws.name)?;
+                }
+                Staleness::ContainersMissing(missing) => {
+                    let mut repaired = ws.clone();
+                    repaired
+                        .resources
+                        .retain(|r| !missing.contains(&r.service_name));
+                    state_manager.register_workspace(repaired)?;
+                }
+            }
+        }
+
+        stale_workspaces.push(StaleWorkspaceReport {
+            name: ws.name.clone(),
+            reason: reason.to_string(),
+            action: action.to_string(),
+            missing_services,
+            applied: force,
+        });
+    }
+
+    let status = if orphans.is_empty() && stale.is_empty() {
+        "clean"
+    } else if force {
+        "cleaned"
+    } else {
+        "dry_run"
+    };
+
+    if json {
+        let output = CleanupOutput {
+            status: status.to_string(),
+            orphaned_count: orphans.len(),
+            removed,
+            containers,
+            networks,
+            volumes,
+            stale_workspaces,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if status == "cleaned" {
+        println!("\nCleanup complete.");
+    } else if status == "dry_run" {
+        println!("\n(Dry run - use --force to actually remove)");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provisioner::ResourceInfo;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn resource(kind: ResourceKind, namespace: &str, repo: Option<&str>) -> ManagedResource {
+        ManagedResource {
+            kind,
+            id: format!("{}-id", namespace),
+            name: format!("{}-postgres", namespace),
+            namespace: Some(namespace.to_string()),
+            service: Some("postgres".to_string()),
+            repo: repo.map(str::to_string),
+            workspace_name: None,
+            workspace_path: None,
+            image: None,
+        }
+    }
+
+    #[test]
+    fn test_find_orphans_scoped_to_repo() {
+        let resources = vec![
+            resource(ResourceKind::Container, "aether-aaaa-live", Some("aaaa")),
+            resource(ResourceKind::Network, "aether-aaaa-gone", Some("aaaa")),
+            resource(ResourceKind::Container, "aether-bbbb-other", Some("bbbb")),
+            resource(ResourceKind::Volume, "aether-legacy", None),
+        ];
+        let registered = HashSet::from(["aether-aaaa-live".to_string()]);
+
+        let scoped = find_orphans(
+            &resources,
+            &registered,
+            &HashSet::from(["aaaa".to_string()]),
+        );
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(scoped[0].namespace.as_deref(), Some("aether-aaaa-gone"));
+
+        // Repositories whose registry was read; unlabelled resources never count
+        let repos = HashSet::from(["aaaa".to_string(), "bbbb".to_string()]);
+        let all = find_orphans(&resources, &registered, &repos);
+        assert_eq!(all.len(), 2);
+        assert!(all.iter().all(|r| r.repo.is_some()));
+    }
+
+    #[test]
+    fn test_find_staleness() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut ws = WorkspaceState {
+            name: "feature-x".to_string(),
+            path: temp_dir.path().to_string_lossy().to_string(),
+            namespace: "aether-aaaa-feature-x".to_string(),
+            backend_type: "docker".to_string(),
+            created_at: "2026-01-28T00:00:00Z".to_string(),
+            change_id: None,
+            resources: vec![ResourceInfo {
+                service_name: "postgres".to_string(),
+                container_id: "abc123".to_string(),
+                image: "postgres:15".to_string(),
+                port_mappings: HashMap::new(),
+            }],
+        };
+
+        let live = vec![resource(
+            ResourceKind::Container,
+            "aether-aaaa-feature-x",
+            Some("aaaa"),
+        )];
+        assert_eq!(find_staleness(&ws, &live), None);
+        assert_eq!(
+            find_staleness(&ws, &[]),
+            Some(Staleness::ContainersMissing(vec!["postgres".to_string()]))
+        );
+
+        ws.path = temp_dir.path().join("gone").to_string_lossy().to_string();
+        assert_eq!(find_staleness(&ws, &live), Some(Staleness::PathMissing));
+    }
+}
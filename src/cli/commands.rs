@@ -18,6 +18,14 @@ pub struct Cli {
     #[arg(short, long, global = true)]
     pub config: Option<String>,
 
+    /// Workspace to operate on (default: the one containing the current directory)
+    #[arg(long, global = true)]
+    pub workspace: Option<String>,
+
+    /// Config profile to apply (a `[profiles.<name>]` table)
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -49,13 +57,67 @@ pub enum Commands {
     /// List all workspaces
     List,
 
-    /// Cleanup orphaned containers
+    /// Cleanup orphaned containers, networks, volumes and stale registry entries
     Cleanup {
-        /// Actually remove containers (default is dry-run)
+        /// Actually remove resources and fix the registry (default is dry-run)
+        #[arg(long)]
+        force: bool,
+
+        /// Consider containers of every known repository, not just this one
+        #[arg(long)]
+        all_repos: bool,
+    },
+
+    /// Forget finished workspaces along with their infrastructure
+    Gc {
+        /// Collect workspaces whose changes have landed in trunk() or were abandoned
+        #[arg(long)]
+        merged: bool,
+
+        /// Actually forget the workspaces (default is dry-run)
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Undo the last jj operation, reversing the workspace infrastructure it changed
+    Undo,
+
+    /// Reconcile the registry with `jj workspace list`
+    Sync {
+        /// Deprovision infrastructure of workspaces jj no longer knows (default is dry-run)
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Write a starter aether.toml, detected from the project's compose or env example file
+    Init {
+        /// Convert the services of this compose file
+        #[arg(long)]
+        from_compose: Option<String>,
+
+        /// Overwrite an existing config
         #[arg(long)]
         force: bool,
     },
 
+    /// Check and inspect aether.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Export the current workspace for use without ajj
+    Export {
+        #[command(subcommand)]
+        action: ExportAction,
+    },
+
+    /// Inspect and maintain the workspace state registry
+    State {
+        #[command(subcommand)]
+        action: StateAction,
+    },
+
     /// Show logs from a service
     Logs {
         /// Service name
@@ -130,6 +192,73 @@ pub enum WorkspaceAction {
     Forget {
         /// Workspace name or path
         workspace: String,
+
+        /// Remove a git worktree even if it has uncommitted or untracked changes
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Provision infrastructure for an existing jj workspace
+    Attach {
+        /// Path inside the workspace (default: current directory)
+        path: Option<String>,
+
+        /// The jj workspace's name, when it differs from the directory name (default: `default` for the main checkout, else the directory name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Tear down a workspace's infrastructure but keep the jj workspace
+    Detach {
+        /// Workspace name or path (default: current workspace)
+        workspace: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Report every problem in the config with file, line and column
+    Validate,
+
+    /// Print the config
+    Show {
+        /// Merge all layers and annotate each value with where it came from
+        #[arg(long)]
+        resolved: bool,
+    },
+
+    /// Print the JSON Schema of aether.toml, for editor completion and validation
+    Schema {
+        /// Write the schema to this file instead of stdout
+        #[arg(long)]
+        file: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportAction {
+    /// Print the workspace's services, with their host ports, as a docker-compose file
+    Compose {
+        /// Write the compose file to this file instead of stdout
+        #[arg(long)]
+        file: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum StateAction {
+    /// Rebuild the registry from backend labels, keeping a backup of the old one
+    Repair {
+        /// Report differences without rewriting the registry
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Upgrade a registry written by an older ajj to the current schema
+    Migrate {
+        /// List pending migrations without applying them
+        #[arg(long)]
+        dry_run: bool,
     },
 }
 
@@ -150,6 +279,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_workspace_attach_detach() {
+        let cli = Cli::parse_from(["ajj", "workspace", "attach"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Workspace {
+                action: WorkspaceAction::Attach {
+                    path: None,
+                    name: None
+                },
+            }
+        ));
+
+        let cli = Cli::parse_from(["ajj", "workspace", "detach", "feature-x"]);
+        match cli.command {
+            Commands::Workspace {
+                action: WorkspaceAction::Detach { workspace },
+            } => assert_eq!(workspace, Some("feature-x".to_string())),
+            _ => panic!("Wrong command parsed"),
+        }
+    }
+
     #[test]
     fn test_parse_run_command() {
         let cli = Cli::parse_from(["ajj", "run", "--", "cargo", "test"]);
@@ -180,6 +331,19 @@ mod tests {
         assert_eq!(cli.config, Some("/path/to/aether.toml".to_string()));
     }
 
+    #[test]
+    fn test_parse_workspace_flag() {
+        let cli = Cli::parse_from(["ajj", "logs", "postgres", "--workspace", "feature-x"]);
+        assert_eq!(cli.workspace, Some("feature-x".to_string()));
+
+        let cli = Cli::parse_from(["ajj", "--workspace", "feature-x", "log", "-r", "@"]);
+        assert_eq!(cli.workspace, Some("feature-x".to_string()));
+        match cli.command {
+            Commands::Jj(args) => assert_eq!(args, vec!["log", "-r", "@"]),
+            _ => panic!("Wrong command parsed"),
+        }
+    }
+
     #[test]
     fn test_parse_logs_command() {
         let cli = Cli::parse_from(["ajj", "logs", "postgres"]);
@@ -242,6 +406,159 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_cleanup_all_repos() {
+        let cli = Cli::parse_from(["ajj", "cleanup", "--force", "--all-repos"]);
+        match cli.command {
+            Commands::Cleanup { force, all_repos } => {
+                assert!(force);
+                assert!(all_repos);
+            }
+            _ => panic!("Wrong command parsed"),
+        }
+    }
+
+    #[test]
+    fn test_parse_gc() {
+        let cli = Cli::parse_from(["ajj", "gc", "--merged"]);
+        match cli.command {
+            Commands::Gc { merged, force } => {
+                assert!(merged);
+                assert!(!force);
+            }
+            _ => panic!("Wrong command parsed"),
+        }
+    }
+
+    #[test]
+    fn test_parse_undo() {
+        let cli = Cli::parse_from(["ajj", "undo"]);
+        assert!(matches!(cli.command, Commands::Undo));
+    }
+
+    #[test]
+    fn test_parse_sync() {
+        let cli = Cli::parse_from(["ajj", "sync", "--force"]);
+        match cli.command {
+            Commands::Sync { force } => assert!(force),
+            _ => panic!("Wrong command parsed"),
+        }
+    }
+
+    #[test]
+    fn test_parse_workspace_forget() {
+        let cli = Cli::parse_from(["ajj", "workspace", "forget", "feature"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Workspace {
+                action: WorkspaceAction::Forget { force: false, .. }
+            }
+        ));
+
+        let cli = Cli::parse_from(["ajj", "workspace", "forget", "feature", "--force"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Workspace {
+                action: WorkspaceAction::Forget { force: true, .. }
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_init() {
+        let cli = Cli::parse_from(["ajj", "init"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Init {
+                from_compose: None,
+                force: false
+            }
+        ));
+
+        let cli = Cli::parse_from([
+            "ajj",
+            "init",
+            "--from-compose",
+            "docker-compose.yml",
+            "--force",
+        ]);
+        match cli.command {
+            Commands::Init {
+                from_compose,
+                force,
+            } => {
+                assert_eq!(from_compose, Some("docker-compose.yml".to_string()));
+                assert!(force);
+            }
+            _ => panic!("Wrong command parsed"),
+        }
+    }
+
+    #[test]
+    fn test_parse_config_validate() {
+        let cli = Cli::parse_from(["ajj", "config", "validate"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Config {
+                action: ConfigAction::Validate
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_config_show_with_profile() {
+        let cli = Cli::parse_from(["ajj", "config", "show", "--resolved", "--profile", "full"]);
+        assert_eq!(cli.profile, Some("full".to_string()));
+        assert!(matches!(
+            cli.command,
+            Commands::Config {
+                action: ConfigAction::Show { resolved: true }
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_config_schema() {
+        let cli = Cli::parse_from(["ajj", "config", "schema", "--file", "aether.schema.json"]);
+        match cli.command {
+            Commands::Config {
+                action: ConfigAction::Schema { file },
+            } => assert_eq!(file, Some("aether.schema.json".to_string())),
+            _ => panic!("Wrong command parsed"),
+        }
+    }
+
+    #[test]
+    fn test_parse_export_compose() {
+        let cli = Cli::parse_from([
+            "ajj",
+            "export",
+            "compose",
+            "--file",
+            "docker-compose.yml",
+            "--workspace",
+            "feature-x",
+        ]);
+        assert_eq!(cli.workspace, Some("feature-x".to_string()));
+        match cli.command {
+            Commands::Export {
+                action: ExportAction::Compose { file },
+            } => assert_eq!(file, Some("docker-compose.yml".to_string())),
+            _ => panic!("Wrong command parsed"),
+        }
+    }
+
+    #[test]
+    fn test_parse_state_repair() {
+        let cli = Cli::parse_from(["ajj", "state", "repair", "--dry-run"]);
+        match cli.command {
+            Commands::State {
+                action: StateAction::Repair { dry_run },
+            } => assert!(dry_run),
+            _ => panic!("Wrong command parsed"),
+        }
+    }
+
     #[test]
     fn test_parse_exec_command() {
         let cli = Cli::parse_from(["ajj", "exec", "postgres", "--", "psql", "-c", "SELECT 1"]);
@@ -34,7 +34,7 @@ fn generate_bash_completion(path: &Path) -> Result<()> {
     _init_completion || return
 
     case "${prev}" in
-        -c|--config)
+        -c|--config|--file|--from-compose)
             _filedir
             return
             ;;
@@ -43,7 +43,7 @@ fn generate_bash_completion(path: &Path) -> Result<()> {
             return
             ;;
         workspace)
-            COMPREPLY=($(compgen -W "add forget" -- "${cur}"))
+            COMPREPLY=($(compgen -W "add forget attach detach" -- "${cur}"))
             return
             ;;
         logs|restart|stop|start|exec)
@@ -52,13 +52,29 @@ fn generate_bash_completion(path: &Path) -> Result<()> {
             return
             ;;
         cleanup)
-            COMPREPLY=($(compgen -W "--force" -- "${cur}"))
+            COMPREPLY=($(compgen -W "--force --all-repos" -- "${cur}"))
+            return
+            ;;
+        gc)
+            COMPREPLY=($(compgen -W "--merged --force" -- "${cur}"))
+            return
+            ;;
+        init)
+            COMPREPLY=($(compgen -W "--from-compose --force" -- "${cur}"))
+            return
+            ;;
+        config)
+            COMPREPLY=($(compgen -W "validate show schema" -- "${cur}"))
+            return
+            ;;
+        export)
+            COMPREPLY=($(compgen -W "compose" -- "${cur}"))
             return
             ;;
     esac
 
     if [[ ${cword} -eq 1 ]]; then
-        COMPREPLY=($(compgen -W "workspace run status list cleanup logs restart stop start exec --help" -- "${cur}"))
+        COMPREPLY=($(compgen -W "workspace run status list sync undo gc init config export cleanup logs restart stop start exec --help" -- "${cur}"))
     fi
 }
 
@@ -81,7 +97,13 @@ _ajj() {
         'run:Run command with workspace environment'
         'status:Show workspace status'
         'list:List all workspaces'
-        'cleanup:Cleanup orphaned containers'
+        'sync:Reconcile the registry with jj workspaces'
+        'undo:Undo the last jj operation and its infrastructure'
+        'gc:Forget finished workspaces and their infrastructure'
+        'init:Write a starter aether.toml'
+        'config:Check and inspect aether.toml'
+        'export:Export the workspace for use without ajj'
+        'cleanup:Cleanup orphaned resources'
         'logs:Show logs from a service'
         'restart:Restart a service'
         'stop:Stop a service'
@@ -91,7 +113,15 @@ _ajj() {
 
     case $words[2] in
         workspace)
-            subcommands=('add:Create new workspace' 'forget:Remove workspace')
+            subcommands=('add:Create new workspace' 'forget:Remove workspace' 'attach:Provision infrastructure for an existing workspace' 'detach:Remove infrastructure, keep workspace')
+            _describe 'command' subcommands
+            ;;
+        config)
+            subcommands=('validate:Report config problems with file, line and column' 'show:Print the config, --resolved for merged layers with origins' 'schema:Print the JSON Schema of aether.toml')
+            _describe 'command' subcommands
+            ;;
+        export)
+            subcommands=('compose:Print the workspace as a docker-compose file')
             _describe 'command' subcommands
             ;;
         logs|restart|stop|start|exec)
@@ -119,18 +149,36 @@ complete -c ajj -n __fish_use_subcommand -a workspace -d 'Workspace management'
 complete -c ajj -n __fish_use_subcommand -a run -d 'Run command with workspace environment'
 complete -c ajj -n __fish_use_subcommand -a status -d 'Show workspace status'
 complete -c ajj -n __fish_use_subcommand -a list -d 'List all workspaces'
-complete -c ajj -n __fish_use_subcommand -a cleanup -d 'Cleanup orphaned containers'
+complete -c ajj -n __fish_use_subcommand -a sync -d 'Reconcile the registry with jj workspaces'
+complete -c ajj -n __fish_use_subcommand -a undo -d 'Undo the last jj operation and its infrastructure'
+complete -c ajj -n __fish_use_subcommand -a gc -d 'Forget finished workspaces and their infrastructure'
+complete -c ajj -n __fish_use_subcommand -a init -d 'Write a starter aether.toml'
+complete -c ajj -n __fish_use_subcommand -a config -d 'Check and inspect aether.toml'
+complete -c ajj -n __fish_use_subcommand -a export -d 'Export the workspace for use without ajj'
+complete -c ajj -n __fish_use_subcommand -a cleanup -d 'Cleanup orphaned resources'
 complete -c ajj -n __fish_use_subcommand -a logs -d 'Show logs from a service'
 complete -c ajj -n __fish_use_subcommand -a restart -d 'Restart a service'
 complete -c ajj -n __fish_use_subcommand -a stop -d 'Stop a service'
 complete -c ajj -n __fish_use_subcommand -a start -d 'Start a service'
 complete -c ajj -n __fish_use_subcommand -a exec -d 'Execute a command in a service container'
 
-complete -c ajj -n '__fish_seen_subcommand_from workspace' -a add forget
+complete -c ajj -n '__fish_seen_subcommand_from workspace' -a 'add forget attach detach'
+complete -c ajj -n '__fish_seen_subcommand_from init' -l from-compose -r
+complete -c ajj -n '__fish_seen_subcommand_from init' -l force
+complete -c ajj -n '__fish_seen_subcommand_from config' -a 'validate show schema'
+complete -c ajj -n '__fish_seen_subcommand_from show' -l resolved
+complete -c ajj -n '__fish_seen_subcommand_from schema' -l file -r
+complete -c ajj -n '__fish_seen_subcommand_from export' -a 'compose'
+complete -c ajj -n '__fish_seen_subcommand_from compose' -l file -r
 complete -c ajj -n '__fish_seen_subcommand_from logs restart stop start exec' -a 'postgres redis'
 complete -c ajj -n '__fish_seen_subcommand_from cleanup' -l force
+complete -c ajj -n '__fish_seen_subcommand_from cleanup' -l all-repos
+complete -c ajj -n '__fish_seen_subcommand_from gc' -l merged
+complete -c ajj -n '__fish_seen_subcommand_from gc' -l force
 complete -c ajj -s o -l output -x -a 'human json'
 complete -c ajj -s c -l config -r
+complete -c ajj -l workspace -x
+complete -c ajj -l profile -x
 "#;
 
     let mut file = File::create(path)?;
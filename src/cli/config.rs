@@ -0,0 +1,167 @@
+use crate::config::{config_schema, find_config, resolve_config, validate_config, ResolvedConfig};
+use crate::error::{AetherError, Result};
+use crate::output::json::{ConfigSchemaOutput, ConfigShowOutput, ConfigValidateOutput};
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Table};
+
+fn config_file(config_path: Option<&str>) -> Result<PathBuf> {
+    match config_path {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => find_config(Path::new(".")),
+    }
+}
+
+/// Validate the config and report every problem found. Returns the exit
+/// code: 0 when the config is valid, 1 otherwise.
+pub fn handle_config_validate(
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    json: bool,
+) -> Result<i32> {
+    let config_file = config_file(config_path)?;
+    let diagnostics = validate_config(&config_file, profile)?;
+    let valid = diagnostics.is_empty();
+
+    if json {
+        let output = ConfigValidateOutput {
+            status: if valid { "valid" } else { "invalid" }.to_string(),
+            file: config_file.display().to_string(),
+            diagnostics,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if valid {
+        println!("✓ {} is valid", config_file.display());
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+        println!(
+            "⚠ {} problem(s) in {}",
+            diagnostics.len(),
+            config_file.display()
+        );
+    }
+
+    Ok(if valid { 0 } else { 1 })
+}
+
+/// Print the config file, or with `resolved` every layer merged and each
+/// value annotated with the file, line and column it came from.
+pub fn handle_config_show(
+    resolved: bool,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let config_file = config_file(config_path)?;
+
+    if !resolved {
+        let source = std::fs::read_to_string(&config_file)
+            .map_err(|e| AetherError::Config(format!("Failed to read config: {}", e)))?;
+        if json {
+            let config: toml::Value = toml::from_str(&source)
+                .map_err(|e| AetherError::Config(format!("Failed to parse TOML: {}", e)))?;
+            let output = ConfigShowOutput {
+                file: config_file.display().to_string(),
+                profile: None,
+                layers: vec![config_file.display().to_string()],
+                config: serde_json::to_value(config)?,
+                origins: Default::default(),
+            };
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            print!("{}", source);
+        }
+        return Ok(());
+    }
+
+    let resolved = resolve_config(&config_file, profile)?;
+    let origins = resolved.origins();
+
+    if json {
+        let config: toml::Value = toml::from_str(&resolved.document.to_string())
+            .map_err(|e| AetherError::Config(format!("Failed to parse TOML: {}", e)))?;
+        let output = ConfigShowOutput {
+            file: config_file.display().to_string(),
+            profile: resolved.profile.clone(),
+            layers: resolved.layers.iter().map(|l| l.label()).collect(),
+            config: serde_json::to_value(config)?,
+            origins: origins.into_iter().collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        for layer in &resolved.layers {
+            println!("# layer: {}", layer.label());
+        }
+        println!();
+        print!("{}", annotated(&resolved, &origins));
+        for diagnostic in &resolved.diagnostics {
+            eprintln!("⚠ {}", diagnostic);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the JSON Schema of `aether.toml`, or write it to `file`.
+pub fn handle_config_schema(file: Option<&str>, json: bool) -> Result<()> {
+    let schema = serde_json::to_string_pretty(&config_schema())?;
+    let Some(file) = file else {
+        println!("{}", schema);
+        return Ok(());
+    };
+
+    std::fs::write(file, format!("{}\n", schema))?;
+    if json {
+        let output = ConfigSchemaOutput {
+            file: file.to_string(),
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!("✓ Wrote JSON Schema to {}", file);
+    }
+    Ok(())
+}
+
+/// The merged document with a `# file:line:column` comment after each value.
+fn annotated(resolved: &ResolvedConfig, origins: &[(String, String)]) -> String {
+    fn annotate(table: &mut Table, path: &mut Vec<String>, origins: &[(String, String)]) {
+        for (mut key, item) in table.iter_mut() {
+            path.push(key.get().to_string());
+            match item {
+                Item::Table(child) => annotate(child, path, origins),
+                Item::Value(value) => {
+                    let dotted = path.join(".");
+                    if let Some((_, origin)) = origins.iter().find(|(p, _)| *p == dotted) {
+                        value.decor_mut().set_suffix(format!("  # {}", origin));
+                    }
+                    key.fmt();
+                }
+                _ => {}
+            }
+            path.pop();
+        }
+    }
+
+    let mut document: DocumentMut = resolved.document.clone();
+    annotate(document.as_table_mut(), &mut Vec::new(), origins);
+    document.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::resolve_layers;
+
+    #[test]
+    fn test_annotated_marks_each_value() {
+        let main = Path::new("aether.toml");
+        let source = "[backend]\ntype = \"docker\"\n\n[services.redis]\nimage = \"redis:7\"\n";
+        let resolved =
+            resolve_layers(main, source.to_string(), Path::new("."), None, None, None).unwrap();
+
+        let shown = annotated(&resolved, &resolved.origins());
+        assert!(shown.contains("type = \"docker\"  # aether.toml:2:1"));
+        assert!(shown.contains("image = \"redis:7\"  # aether.toml:5:1"));
+    }
+}
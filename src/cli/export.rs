@@ -0,0 +1,96 @@
+use super::resolve_workspace;
+use super::workspace::{load_workspace_config, service_spec};
+use crate::backend::{render_compose, ResourceOwner};
+use crate::error::{AetherError, Result};
+use crate::output::json::ExportComposeOutput;
+use crate::provisioner::StateManager;
+use crate::repo::find_repo_root;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Print the workspace's services as a docker-compose project, or write it
+/// to `file`. Host ports are the ones allocated when the workspace was
+/// provisioned; the config is read from the workspace's checkout.
+pub fn handle_export_compose(
+    file: Option<&str>,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    workspace: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let repo_root = find_repo_root(Path::new("."))?;
+    let state_manager = StateManager::new(&repo_root);
+    let workspace_state = resolve_workspace(&state_manager, workspace)?.ok_or_else(|| {
+        AetherError::State(
+            "No Aether-managed workspace contains the current directory. \
+             Run from inside one or pass --workspace <name>"
+                .to_string(),
+        )
+    })?;
+    let config = load_workspace_config(config_path, profile, Path::new(&workspace_state.path))?;
+
+    let mut services = HashMap::new();
+    let mut warnings = Vec::new();
+    for (name, svc_config) in &config.services {
+        let port_mappings = workspace_state
+            .resources
+            .iter()
+            .find(|r| &r.service_name == name)
+            .map(|r| r.port_mappings.clone())
+            .unwrap_or_default();
+        let spec = service_spec(
+            name,
+            svc_config,
+            port_mappings,
+            Path::new(&workspace_state.path),
+        )?;
+        for port in spec
+            .ports
+            .iter()
+            .filter(|p| !spec.port_mappings.contains_key(p))
+        {
+            warnings.push(format!(
+                "service `{}`: no host port was allocated for {}; compose will pick one",
+                name, port
+            ));
+        }
+        services.insert(name.clone(), spec);
+    }
+
+    let owner = ResourceOwner {
+        repo_id: state_manager.repo_hash().to_string(),
+        workspace_name: workspace_state.name.clone(),
+        workspace_path: workspace_state.path.clone(),
+    };
+    let rendered = render_compose(&workspace_state.namespace, &owner, &services)?;
+
+    let Some(file) = file else {
+        print!("{}", rendered);
+        for warning in &warnings {
+            eprintln!("⚠ {}", warning);
+        }
+        return Ok(());
+    };
+
+    std::fs::write(file, &rendered)?;
+    if json {
+        let output = ExportComposeOutput {
+            file: file.to_string(),
+            workspace: workspace_state.name,
+            services: services.len(),
+            warnings,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!(
+            "✓ Exported {} service(s) of workspace '{}' to {}",
+            services.len(),
+            workspace_state.name,
+            file
+        );
+        for warning in &warnings {
+            println!("⚠ {}", warning);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use super::workspace::forget_registered;
+use crate::error::{AetherError, Result};
+use crate::jj::{query, JjCommit, JjWorkspace};
+use crate::output::json::{GcCandidate, GcOutput};
+use crate::provisioner::{StateManager, WorkspaceState};
+use crate::repo::{detect_vcs_kind, find_repo_root, VcsKind};
+use crate::vcs::JjVcs;
+use std::path::Path;
+
+#[derive(Debug, PartialEq)]
+enum GcReason {
+    /// Nothing left in the working copy and everything below it is in trunk
+    Merged,
+    /// The change the workspace was created at has been abandoned, and
+    /// nothing is left outside trunk
+    Abandoned,
+}
+
+impl GcReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Merged => "merged",
+            Self::Abandoned => "abandoned",
+        }
+    }
+}
+
+/// Decide whether a workspace is done with.
+///
+/// A freshly added workspace also sits empty on top of trunk, so a working
+/// copy still at the change recorded at creation only counts as finished once
+/// that change is gone. Without a recorded change the two cannot be told
+/// apart, so such a workspace is never finished.
+fn gc_reason(
+    target: &JjCommit,
+    recorded_change: Option<&str>,
+    parents_in_trunk: bool,
+    recorded_visible: bool,
+) -> Option<GcReason> {
+    let recorded_change = recorded_change?;
+    // Work left in the working copy or below it is never collected
+    if !target.empty || !parents_in_trunk {
+        return None;
+    }
+    if !recorded_visible {
+        return Some(GcReason::Abandoned);
+    }
+    let untouched = recorded_change == target.change_id;
+    if !untouched {
+        return Some(GcReason::Merged);
+    }
+    None
+}
+
+fn merged_candidate(
+    repo_root: &Path,
+    registered: &WorkspaceState,
+    jj_workspace: &JjWorkspace,
+) -> Result<Option<GcReason>> {
+    let target = &jj_workspace.target;
+
+    let outside_trunk = query::commits(
+        repo_root,
+        &format!("parents({}) ~ ::trunk()", target.commit_id),
+    )?;
+    let recorded_visible = match &registered.change_id {
+        Some(change_id) => {
+            !query::commits(repo_root, &format!("present({})", change_id))?.is_empty()
+        }
+        None => true,
+    };
+
+    Ok(gc_reason(
+        target,
+        registered.change_id.as_deref(),
+        outside_trunk.is_empty(),
+        recorded_visible,
+    ))
+}
+
+pub async fn handle_gc(merged: bool, force: bool, json: bool) -> Result<()> {
+    if !merged {
+        return Err(AetherError::Config(
+            "Nothing to collect. Use `ajj gc --merged` to collect workspaces whose changes have landed".into(),
+        ));
+    }
+
+    if detect_vcs_kind(Path::new("."))? != VcsKind::Jj {
+        return Err(AetherError::Config(
+            "`ajj gc --merged` relies on jj revsets and needs a jj repository".into(),
+        ));
+    }
+    let repo_root = find_repo_root(Path::new("."))?;
+    let vcs = JjVcs::new(&repo_root);
+    let state_manager = StateManager::new(&repo_root);
+    let jj_workspaces = query::workspaces(&repo_root)?;
+    let mut registered = state_manager.list_workspaces()?;
+    registered.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // 1. Find finished workspaces; the main checkout is never collected
+    let mut candidates = Vec::new();
+    let mut warnings = Vec::new();
+    for ws in registered.iter().filter(|ws| ws.name != "default") {
+        let Some(jj_workspace) = jj_workspaces.iter().find(|jj| jj.name == ws.name) else {
+            continue;
+        };
+        if ws.change_id.is_none() {
+            warnings.push(format!(
+                "'{}' was registered without its starting change and is never collected \
+                 (use `ajj workspace forget {}`)",
+                ws.name, ws.name
+            ));
+            continue;
+        }
+        if let Some(reason) = merged_candidate(&repo_root, ws, jj_workspace)? {
+            candidates.push((ws, reason, jj_workspace.target.change_id.clone()));
+        }
+    }
+
+    if !json {
+        if candidates.is_empty() {
+            println!("No finished workspaces to collect.");
+        }
+        for (ws, reason, _) in &candidates {
+            println!("  {} ({})", ws.name, reason.as_str());
+        }
+        for warning in &warnings {
+            println!("⚠ {}", warning);
+        }
+    }
+
+    // 2. Forget them along with their infrastructure
+    let mut forgotten = Vec::new();
+    if force {
+        for (ws, _, _) in &candidates {
+            forget_registered(&vcs, &state_manager, ws, false).await?;
+            forgotten.push(ws.name.clone());
+            if !json {
+                println!("✓ Workspace '{}' forgotten", ws.name);
+            }
+        }
+    }
+
+    let status = if candidates.is_empty() {
+        "clean"
+    } else if force {
+        "collected"
+    } else {
+        "dry_run"
+    };
+
+    if json {
+        let output = GcOutput {
+            status: status.to_string(),
+            candidates: candidates
+                .iter()
+                .map(|(ws, reason, change_id)| GcCandidate {
+                    name: ws.name.clone(),
+                    reason: reason.as_str().to_string(),
+                    change_id: change_id.clone(),
+                })
+                .collect(),
+            forgotten,
+            warnings,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if status == "dry_run" {
+        println!("\n(Dry run - use --force to forget these workspaces and their infrastructure)");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(change_id: &str, empty: bool) -> JjCommit {
+        JjCommit {
+            change_id: change_id.to_string(),
+            commit_id: "3d4e5f60".to_string(),
+            description: String::new(),
+            bookmarks: vec![],
+            conflict: false,
+            empty,
+        }
+    }
+
+    #[test]
+    fn test_gc_reason() {
+        // Work landed and the agent moved on to a new empty change
+        assert_eq!(
+            gc_reason(&commit("new", true), Some("orig"), true, true),
+            Some(GcReason::Merged)
+        );
+        // Fresh workspace on top of trunk with nothing done yet
+        assert_eq!(
+            gc_reason(&commit("orig", true), Some("orig"), true, true),
+            None
+        );
+        // Pending work below the working copy
+        assert_eq!(
+            gc_reason(&commit("new", true), Some("orig"), false, true),
+            None
+        );
+        // Uncommitted work in the working copy
+        assert_eq!(
+            gc_reason(&commit("new", false), Some("orig"), true, true),
+            None
+        );
+        // Starting change rewritten (squashed, described) with work pending
+        assert_eq!(
+            gc_reason(&commit("new", true), Some("orig"), false, false),
+            None
+        );
+        assert_eq!(
+            gc_reason(&commit("new", true), Some("orig"), true, false),
+            Some(GcReason::Abandoned)
+        );
+        // Registered before change ids were recorded: never collected
+        assert_eq!(gc_reason(&commit("new", true), None, true, true), None);
+        assert_eq!(gc_reason(&commit("new", true), None, false, false), None);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::config::{detect_scaffold, render_scaffold, scaffold_from_compose, validate_source};
+use crate::error::{AetherError, Result};
+use crate::output::json::InitOutput;
+use std::path::{Path, PathBuf};
+
+/// Add the per-developer `aether.local.toml` of `config_file` to the
+/// `.gitignore` next to it, which jj honours too. Returns the `.gitignore`
+/// when it had to be changed.
+fn ignore_local_config(config_file: &Path) -> Result<Option<PathBuf>> {
+    let Some(stem) = config_file.file_stem().and_then(|s| s.to_str()) else {
+        return Ok(None);
+    };
+    let entry = format!("{}.local.toml", stem);
+    let gitignore = config_file.with_file_name(".gitignore");
+
+    let mut content = std::fs::read_to_string(&gitignore).unwrap_or_default();
+    let ignored = content
+        .lines()
+        .map(|line| line.trim().trim_start_matches('/'))
+        .any(|line| line == entry);
+    if ignored {
+        return Ok(None);
+    }
+
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&entry);
+    content.push('\n');
+    std::fs::write(&gitignore, content)?;
+    Ok(Some(gitignore))
+}
+
+/// Write a starter config to `config_path` (default `./aether.toml`),
+/// converted from the compose file `from_compose` or detected from the
+/// files next to it.
+pub fn handle_init(
+    config_path: Option<&str>,
+    from_compose: Option<&str>,
+    force: bool,
+    json: bool,
+) -> Result<()> {
+    let config_file = PathBuf::from(config_path.unwrap_or("aether.toml"));
+    if config_file.exists() && !force {
+        return Err(AetherError::Config(format!(
+            "{} already exists (use --force to overwrite)",
+            config_file.display()
+        )));
+    }
+
+    let scaffold = match from_compose {
+        Some(compose) => scaffold_from_compose(Path::new(compose))?,
+        None => {
+            let dir = config_file
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            detect_scaffold(dir)?
+        }
+    };
+
+    let rendered = render_scaffold(&scaffold);
+    std::fs::write(&config_file, &rendered)?;
+    let gitignore = ignore_local_config(&config_file)?;
+    let diagnostics = validate_source(&rendered, &config_file.display().to_string());
+    let services: Vec<String> = scaffold.services.iter().map(|(n, _)| n.clone()).collect();
+
+    if json {
+        let output = InitOutput {
+            status: "created".to_string(),
+            file: config_file.display().to_string(),
+            source: scaffold.source.map(|s| s.display().to_string()),
+            services,
+            warnings: scaffold.warnings,
+            diagnostics,
+            gitignore: gitignore.map(|p| p.display().to_string()),
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    match &scaffold.source {
+        Some(source) if !services.is_empty() => println!(
+            "✓ Wrote {} with {} service(s) from {}: {}",
+            config_file.display(),
+            services.len(),
+            source.display(),
+            services.join(", ")
+        ),
+        _ => println!(
+            "✓ Wrote {} (no services detected; add them under [services])",
+            config_file.display()
+        ),
+    }
+    if let Some(gitignore) = &gitignore {
+        println!("✓ Added the local override file to {}", gitignore.display());
+    }
+    for warning in &scaffold.warnings {
+        println!("⚠ {}", warning);
+    }
+    for diagnostic in &diagnostics {
+        println!("⚠ {}", diagnostic);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_ignore_local_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("aether.toml");
+        let gitignore = temp_dir.path().join(".gitignore");
+        std::fs::write(&gitignore, "target/").unwrap();
+
+        assert_eq!(
+            ignore_local_config(&config_file).unwrap(),
+            Some(gitignore.clone())
+        );
+        assert_eq!(
+            std::fs::read_to_string(&gitignore).unwrap(),
+            "target/\naether.local.toml\n"
+        );
+
+        // Already ignored, left alone
+        assert_eq!(ignore_local_config(&config_file).unwrap(), None);
+        std::fs::write(&gitignore, "/aether.local.toml\n").unwrap();
+        assert_eq!(ignore_local_config(&config_file).unwrap(), None);
+    }
+}
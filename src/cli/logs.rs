@@ -1,3 +1,4 @@
+use super::resolve_workspace;
 use crate::backend::{Backend, DockerBackend};
 use crate::error::Result;
 use crate::provisioner::{StateManager, WorkspaceState};
@@ -29,32 +30,29 @@ pub struct ContainerRunOutput {
     pub stderr: String,
 }
 
-/// Get the current workspace state from the current directory
-fn get_current_workspace() -> Result<(String, WorkspaceState)> {
+/// Get the workspace state for `--workspace` or the current directory
+fn get_current_workspace(workspace: Option<&str>) -> Result<(String, WorkspaceState)> {
     let repo_root = find_repo_root(Path::new("."))?;
     let state_manager = StateManager::new(&repo_root);
 
-    let current_dir = std::env::current_dir()?;
-    let workspace_name = current_dir
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
-
-    let workspace_state = state_manager
-        .get_workspace(&workspace_name)?
-        .ok_or_else(|| {
-            crate::error::AetherError::State(format!(
-                "Workspace '{}' not found. Are you in an Aether-managed workspace?",
-                workspace_name
-            ))
-        })?;
-
-    Ok((workspace_name, workspace_state))
+    let workspace_state = resolve_workspace(&state_manager, workspace)?.ok_or_else(|| {
+        crate::error::AetherError::State(
+            "No Aether-managed workspace contains the current directory. \
+             Run from inside one or pass --workspace <name>"
+                .to_string(),
+        )
+    })?;
+
+    Ok((workspace_state.name.clone(), workspace_state))
 }
 
-pub async fn handle_logs(service: &str, tail: Option<usize>, json: bool) -> Result<()> {
-    let (_workspace_name, workspace_state) = get_current_workspace()?;
+pub async fn handle_logs(
+    service: &str,
+    tail: Option<usize>,
+    workspace: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let (_workspace_name, workspace_state) = get_current_workspace(workspace)?;
 
     let backend = DockerBackend::new()?;
     let logs = backend
@@ -77,8 +75,8 @@ pub async fn handle_logs(service: &str, tail: Option<usize>, json: bool) -> Resu
     Ok(())
 }
 
-pub async fn handle_restart(service: &str, json: bool) -> Result<()> {
-    let (_workspace_name, workspace_state) = get_current_workspace()?;
+pub async fn handle_restart(service: &str, workspace: Option<&str>, json: bool) -> Result<()> {
+    let (_workspace_name, workspace_state) = get_current_workspace(workspace)?;
 
     let backend = DockerBackend::new()?;
     backend.restart(&workspace_state.namespace, service).await?;
@@ -97,8 +95,8 @@ pub async fn handle_restart(service: &str, json: bool) -> Result<()> {
     Ok(())
 }
 
-pub async fn handle_stop(service: &str, json: bool) -> Result<()> {
-    let (_workspace_name, workspace_state) = get_current_workspace()?;
+pub async fn handle_stop(service: &str, workspace: Option<&str>, json: bool) -> Result<()> {
+    let (_workspace_name, workspace_state) = get_current_workspace(workspace)?;
 
     let backend = DockerBackend::new()?;
     backend.stop(&workspace_state.namespace, service).await?;
@@ -117,8 +115,8 @@ pub async fn handle_stop(service: &str, json: bool) -> Result<()> {
     Ok(())
 }
 
-pub async fn handle_start(service: &str, json: bool) -> Result<()> {
-    let (_workspace_name, workspace_state) = get_current_workspace()?;
+pub async fn handle_start(service: &str, workspace: Option<&str>, json: bool) -> Result<()> {
+    let (_workspace_name, workspace_state) = get_current_workspace(workspace)?;
 
     let backend = DockerBackend::new()?;
     backend.start(&workspace_state.namespace, service).await?;
@@ -137,8 +135,13 @@ pub async fn handle_start(service: &str, json: bool) -> Result<()> {
     Ok(())
 }
 
-pub async fn handle_container_run(service: &str, command: &[String], json: bool) -> Result<()> {
-    let (_workspace_name, workspace_state) = get_current_workspace()?;
+pub async fn handle_container_run(
+    service: &str,
+    command: &[String],
+    workspace: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let (_workspace_name, workspace_state) = get_current_workspace(workspace)?;
 
     let backend = DockerBackend::new()?;
     let result = backend
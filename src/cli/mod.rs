@@ -1,32 +1,78 @@
+pub mod cleanup;
 pub mod commands;
 pub mod completion;
+pub mod config;
+pub mod export;
+pub mod gc;
+pub mod init;
 pub mod logs;
+pub mod passthrough;
 pub mod run;
+pub mod state;
 pub mod status;
+pub mod sync;
+pub mod undo;
 pub mod workspace;
 
+pub use cleanup::*;
 pub use commands::*;
 pub use completion::*;
+pub use config::*;
+pub use export::*;
+pub use gc::*;
+pub use init::*;
 pub use logs::*;
+pub use passthrough::*;
 pub use run::*;
+pub use state::*;
 pub use status::*;
+pub use sync::*;
+pub use undo::*;
 pub use workspace::*;
 
-use crate::error::Result;
-use crate::output::json::CleanupOutput;
-use crate::provisioner::StateManager;
+use crate::error::{AetherError, Result};
+use crate::output::json::ListOutput;
+use crate::provisioner::{StateManager, WorkspaceState};
 use crate::repo::find_repo_root;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Resolve the workspace a command applies to: the `--workspace` override if
+/// given, otherwise the registered workspace containing the current directory.
+pub fn resolve_workspace(
+    state_manager: &StateManager,
+    workspace: Option<&str>,
+) -> Result<Option<WorkspaceState>> {
+    match workspace {
+        Some(name) => state_manager
+            .get_workspace(name)?
+            .map(Some)
+            .ok_or_else(|| AetherError::State(format!("Workspace '{}' not found", name))),
+        None => state_manager.find_workspace_by_path(&std::env::current_dir()?),
+    }
+}
+
+/// Root directory of the registered workspace `name`.
+pub fn workspace_path(name: &str) -> Result<PathBuf> {
+    let state_manager = StateManager::new(&find_repo_root(Path::new("."))?);
+    resolve_workspace(&state_manager, Some(name))?
+        .map(|ws| PathBuf::from(ws.path))
+        .ok_or_else(|| AetherError::State(format!("Workspace '{}' not found", name)))
+}
 
 pub async fn handle_list(json: bool) -> Result<()> {
     let repo_root = find_repo_root(Path::new("."))?;
     let state_manager = StateManager::new(&repo_root);
 
     let workspaces = state_manager.list_workspaces()?;
+    let drift = detect_drift(&repo_root);
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&workspaces)?);
-    } else if workspaces.is_empty() {
+        let output = ListOutput { workspaces, drift };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if workspaces.is_empty() {
         println!("No workspaces registered.");
     } else {
         println!("=== Workspaces ===");
@@ -39,126 +85,14 @@ pub async fn handle_list(json: bool) -> Result<()> {
             );
             println!("    path: {}", ws.path);
             println!("    namespace: {}", ws.namespace);
-        }
-    }
-
-    Ok(())
-}
-
-pub async fn handle_cleanup(force: bool, json: bool) -> Result<()> {
-    use bollard::container::ListContainersOptions;
-    use bollard::Docker;
-    use std::collections::HashMap;
-
-    let repo_root = find_repo_root(Path::new("."))?;
-    let state_manager = StateManager::new(&repo_root);
-    let registered_workspaces = state_manager.list_workspaces()?;
-
-    let registered_namespaces: std::collections::HashSet<_> = registered_workspaces
-        .iter()
-        .map(|ws| ws.namespace.clone())
-        .collect();
-
-    let docker = Docker::connect_with_local_defaults()
-        .map_err(|e| crate::error::AetherError::Backend(format!("Docker connect failed: {}", e)))?;
-
-    let mut filters = HashMap::new();
-    filters.insert("label".to_string(), vec!["aether.managed=true".to_string()]);
-
-    let containers = docker
-        .list_containers(Some(ListContainersOptions {
-            all: true,
-            filters,
-            ..Default::default()
-        }))
-        .await
-        .map_err(|e| {
-            crate::error::AetherError::Backend(format!("List containers failed: {}", e))
-        })?;
-
-    let mut orphans = Vec::new();
-    for container in &containers {
-        if let Some(labels) = &container.labels {
-            if let Some(namespace) = labels.get("aether.namespace") {
-                if !registered_namespaces.contains(namespace) {
-                    orphans.push((
-                        container.id.clone().unwrap_or_default(),
-                        namespace.clone(),
-                        labels.get("aether.service").cloned().unwrap_or_default(),
-                    ));
-                }
+            if let Some(change_id) = &ws.change_id {
+                println!("    created at change: {}", change_id);
             }
         }
     }
 
-    if orphans.is_empty() {
-        if json {
-            let output = CleanupOutput {
-                status: "clean".to_string(),
-                orphaned_count: 0,
-                removed: vec![],
-            };
-            println!("{}", serde_json::to_string_pretty(&output)?);
-        } else {
-            println!("No orphaned containers found.");
-        }
-        return Ok(());
-    }
-
-    if !json {
-        println!("Found {} orphaned container(s):", orphans.len());
-        for (id, namespace, service) in &orphans {
-            let short_id = &id[..12.min(id.len())];
-            println!("  - {} ({}/{})", short_id, namespace, service);
-        }
-    }
-
-    let mut removed = Vec::new();
-
-    if force {
-        if !json {
-            println!("\nRemoving orphaned containers...");
-        }
-
-        for (id, _namespace, _) in &orphans {
-            docker
-                .remove_container(
-                    id,
-                    Some(bollard::container::RemoveContainerOptions {
-                        force: true,
-                        ..Default::default()
-                    }),
-                )
-                .await
-                .map_err(|e| {
-                    crate::error::AetherError::Backend(format!("Remove container failed: {}", e))
-                })?;
-            let short_id = id[..12.min(id.len())].to_string();
-            removed.push(short_id.clone());
-            if !json {
-                println!("  Removed: {}", short_id);
-            }
-        }
-
-        if json {
-            let output = CleanupOutput {
-                status: "cleaned".to_string(),
-                orphaned_count: orphans.len(),
-                removed,
-            };
-            println!("{}", serde_json::to_string_pretty(&output)?);
-        } else {
-            println!("Cleanup complete.");
-        }
-    } else if json {
-        let output = CleanupOutput {
-            status: "dry_run".to_string(),
-            orphaned_count: orphans.len(),
-            removed: vec![],
-        };
-        println!("{}", serde_json::to_string_pretty(&output)?);
-    } else {
-        println!("\n(Dry run - use --force to actually remove)");
+    if let Some(drift) = &drift {
+        print_drift_notice(drift);
     }
 
     Ok(())
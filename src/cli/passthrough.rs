@@ -0,0 +1,185 @@
+use super::workspace_path;
+use crate::error::{AetherError, Result};
+use crate::jj::{
+    parse_diff_stat, parse_diff_summary, parse_json_lines, query, JjCommand, JjOutput,
+};
+use crate::output::json::{JjData, JjPassthroughOutput};
+use std::path::{Path, PathBuf};
+
+/// Passthrough commands with a structured JSON form.
+#[derive(Debug, PartialEq)]
+enum Structured {
+    Log,
+    Status,
+    DiffStat,
+    OpLog,
+    WorkspaceList,
+}
+
+fn has_template(args: &[String]) -> bool {
+    args.iter()
+        .any(|a| a.starts_with("-T") || a.starts_with("--template"))
+}
+
+fn classify(args: &[String]) -> Option<Structured> {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        ["log", ..] if !has_template(args) => Some(Structured::Log),
+        ["status" | "st", ..] => Some(Structured::Status),
+        ["diff", rest @ ..] if rest.contains(&"--stat") => Some(Structured::DiffStat),
+        ["op" | "operation", "log", ..] if !has_template(args) => Some(Structured::OpLog),
+        ["workspace", "list", ..] if !has_template(args) => Some(Structured::WorkspaceList),
+        _ => None,
+    }
+}
+
+fn command(args: Vec<String>, dir: Option<&Path>) -> JjCommand {
+    let cmd = JjCommand::new(args);
+    match dir {
+        Some(dir) => cmd.in_dir(dir),
+        None => cmd,
+    }
+}
+
+/// `args` plus `--no-graph` (unless already given) and `-T template`.
+fn templated(args: &[String], template: &str) -> Vec<String> {
+    let mut args = args.to_vec();
+    if !args.iter().any(|a| a == "--no-graph") {
+        args.push("--no-graph".to_string());
+    }
+    args.push("-T".to_string());
+    args.push(template.to_string());
+    args
+}
+
+fn structured_data(
+    kind: Structured,
+    args: &[String],
+    dir: Option<&Path>,
+) -> Result<(JjData, JjOutput)> {
+    Ok(match kind {
+        Structured::Log => {
+            let output =
+                command(templated(args, &query::commit_lines_template()), dir).execute()?;
+            let commits = parse_json_lines(&output.stdout)?;
+            (JjData::Log { commits }, output)
+        }
+        Structured::Status => {
+            let working_copy = query::working_copy(dir.unwrap_or(Path::new(".")))?;
+            // `jj status` takes the same path filters as `jj diff`
+            let mut diff_args = vec!["diff".to_string(), "--summary".to_string()];
+            diff_args.extend(args.iter().skip(1).cloned());
+            let output = command(diff_args, dir).execute()?;
+            let changes = parse_diff_summary(&output.stdout);
+            (
+                JjData::Status {
+                    working_copy,
+                    changes,
+                },
+                output,
+            )
+        }
+        Structured::DiffStat => {
+            let output = command(args.to_vec(), dir).execute()?;
+            let stat = parse_diff_stat(&output.stdout);
+            (JjData::DiffStat { stat }, output)
+        }
+        Structured::OpLog => {
+            let output =
+                command(templated(args, &query::operation_lines_template()), dir).execute()?;
+            let operations = parse_json_lines(&output.stdout)?;
+            (JjData::OpLog { operations }, output)
+        }
+        Structured::WorkspaceList => {
+            let mut list_args = args.to_vec();
+            list_args.push("-T".to_string());
+            list_args.push(query::workspace_template());
+            let output = command(list_args, dir).execute()?;
+            let workspaces = parse_json_lines(&output.stdout)?;
+            (JjData::WorkspaceList { workspaces }, output)
+        }
+    })
+}
+
+/// Run a jj passthrough command in JSON mode and print the envelope.
+///
+/// `log`, `status`, `diff --stat`, `op log` and `workspace list` get a
+/// structured `data` field; anything else carries jj's raw output. Returns
+/// jj's exit code, which the caller exits with.
+pub fn handle_jj_json(args: Vec<String>, workspace: Option<&str>) -> Result<i32> {
+    let dir: Option<PathBuf> = workspace.map(workspace_path).transpose()?;
+    let dir = dir.as_deref();
+
+    let result = match classify(&args) {
+        Some(kind) => structured_data(kind, &args, dir).map(|(data, output)| (Some(data), output)),
+        None => command(args.clone(), dir)
+            .execute()
+            .map(|output| (None, output)),
+    };
+
+    let envelope = match result {
+        Ok((data, output)) => JjPassthroughOutput {
+            status: "ok".to_string(),
+            command: args,
+            exit_code: 0,
+            stdout: if data.is_some() {
+                String::new()
+            } else {
+                output.stdout
+            },
+            data,
+            stderr: output.stderr,
+        },
+        // jj ran and failed; report it in the envelope rather than as an ajj error
+        Err(AetherError::Jj { message, exit_code }) if exit_code >= 0 => JjPassthroughOutput {
+            status: "error".to_string(),
+            command: args,
+            exit_code,
+            data: None,
+            stdout: String::new(),
+            stderr: message,
+        },
+        Err(e) => return Err(e),
+    };
+
+    println!("{}", serde_json::to_string_pretty(&envelope)?);
+    Ok(envelope.exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify(&args(&["log", "-r", "::@"])),
+            Some(Structured::Log)
+        );
+        assert_eq!(classify(&args(&["log", "-T", "change_id"])), None);
+        assert_eq!(classify(&args(&["st"])), Some(Structured::Status));
+        assert_eq!(
+            classify(&args(&["diff", "-r", "@-", "--stat"])),
+            Some(Structured::DiffStat)
+        );
+        assert_eq!(classify(&args(&["diff"])), None);
+        assert_eq!(classify(&args(&["op", "log"])), Some(Structured::OpLog));
+        assert_eq!(
+            classify(&args(&["workspace", "list"])),
+            Some(Structured::WorkspaceList)
+        );
+        assert_eq!(classify(&args(&["describe", "-m", "wip"])), None);
+    }
+
+    #[test]
+    fn test_templated_keeps_user_no_graph() {
+        let built = templated(&args(&["log", "--no-graph"]), "t");
+        assert_eq!(built, args(&["log", "--no-graph", "-T", "t"]));
+        let built = templated(&args(&["op", "log"]), "t");
+        assert_eq!(built, args(&["op", "log", "--no-graph", "-T", "t"]));
+    }
+}
@@ -1,21 +1,31 @@
+use super::workspace_path;
 use crate::error::Result;
+use crate::repo::find_workspace_root;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
-pub fn handle_run(command_args: &[String]) -> Result<i32> {
+pub fn handle_run(command_args: &[String], workspace: Option<&str>) -> Result<i32> {
     if command_args.is_empty() {
         return Err(crate::error::AetherError::Config(
             "No command provided".into(),
         ));
     }
 
-    // 1. Load .env file from current directory
-    let env_vars = load_env_file(".env")?;
+    // 1. Load .env from the selected workspace, or the one we are inside of
+    let selected = workspace.map(workspace_path).transpose()?;
+    let env_root = match &selected {
+        Some(path) => path.clone(),
+        None => find_workspace_root(Path::new(".")).unwrap_or_else(|_| PathBuf::from(".")),
+    };
+    let env_vars = load_env_file(&env_root.join(".env"))?;
 
     // 2. Merge with system environment
     let mut child = Command::new(&command_args[0]);
     child.args(&command_args[1..]);
+    if let Some(path) = &selected {
+        child.current_dir(path);
+    }
 
     for (key, value) in env_vars {
         child.env(key, value);
@@ -31,10 +41,10 @@ pub fn handle_run(command_args: &[String]) -> Result<i32> {
     Ok(status.code().unwrap_or(1))
 }
 
-fn load_env_file(path: &str) -> Result<HashMap<String, String>> {
+pub(super) fn load_env_file(path: &Path) -> Result<HashMap<String, String>> {
     let mut env_vars = HashMap::new();
 
-    if !Path::new(path).exists() {
+    if !path.exists() {
         return Ok(env_vars); // No .env file is okay
     }
 
@@ -47,20 +57,34 @@ fn load_env_file(path: &str) -> Result<HashMap<String, String>> {
         }
 
         if let Some((key, value)) = line.split_once('=') {
-            env_vars.insert(key.trim().to_string(), value.trim().to_string());
+            env_vars.insert(key.trim().to_string(), unquote(value.trim()).to_string());
         }
     }
 
     Ok(env_vars)
 }
 
+/// `value` without one pair of matching surrounding quotes, as compose
+/// reads env files.
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_load_env_file_nonexistent() {
-        let env = load_env_file("nonexistent.env").unwrap();
+        let env = load_env_file(Path::new("nonexistent.env")).unwrap();
         assert_eq!(env.len(), 0);
     }
 
@@ -70,11 +94,18 @@ mod tests {
         use tempfile::NamedTempFile;
 
         let temp_file = NamedTempFile::new().unwrap();
-        fs::write(&temp_file, "KEY1=value1\nKEY2=value2\n# comment\n").unwrap();
+        fs::write(
+            &temp_file,
+            "KEY1=value1\nKEY2=value2\n# comment\nKEY3=\"a b\"\nKEY4='c'\nKEY5=\"d'\n",
+        )
+        .unwrap();
 
-        let env = load_env_file(temp_file.path().to_str().unwrap()).unwrap();
+        let env = load_env_file(temp_file.path()).unwrap();
         assert_eq!(env.get("KEY1"), Some(&"value1".to_string()));
         assert_eq!(env.get("KEY2"), Some(&"value2".to_string()));
-        assert_eq!(env.len(), 2);
+        assert_eq!(env.get("KEY3"), Some(&"a b".to_string()));
+        assert_eq!(env.get("KEY4"), Some(&"c".to_string()));
+        assert_eq!(env.get("KEY5"), Some(&"\"d'".to_string()));
+        assert_eq!(env.len(), 5);
     }
 }
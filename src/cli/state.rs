@@ -0,0 +1,380 @@
+use crate::backend::{Backend, DockerBackend, ManagedResource, ResourceKind};
+use crate::error::Result;
+use crate::output::json::{MigrationInfo, StateMigrateOutput, StateRepairOutput};
+use crate::provisioner::migrations::STATE_VERSION;
+use crate::provisioner::{workspace_namespace, ResourceInfo, StateManager, WorkspaceState};
+use crate::repo::find_repo_root;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Rebuild registry entries from the labels of live containers.
+///
+/// `previous` is whatever could still be read from the old registry; it fills
+/// in `created_at` and `change_id`, and attributes containers provisioned
+/// before workspace labels existed. Containers without a repository label are
+/// kept when their namespace is one this repository gives its workspaces.
+/// Entries without any resources are kept when their path
+/// still exists, since the backend has nothing to say about them.
+/// Returns the rebuilt entries and the containers that could not be placed.
+fn reconstruct(
+    repo_hash: &str,
+    backend_type: &str,
+    containers: Vec<(ManagedResource, HashMap<u16, u16>)>,
+    previous: &[WorkspaceState],
+) -> (Vec<WorkspaceState>, Vec<String>) {
+    let mut rebuilt: BTreeMap<String, WorkspaceState> = BTreeMap::new();
+    let mut skipped = Vec::new();
+
+    for (container, port_mappings) in containers {
+        if container.kind != ResourceKind::Container {
+            continue;
+        }
+        let unlabeled = match container.repo.as_deref() {
+            Some(repo) if repo == repo_hash => false,
+            Some(_) => continue,
+            None => true,
+        };
+        let (Some(namespace), Some(service)) = (&container.namespace, &container.service) else {
+            if !unlabeled {
+                skipped.push(container.name);
+            }
+            continue;
+        };
+
+        let known = previous.iter().find(|ws| &ws.namespace == namespace);
+        if unlabeled
+            && known.is_none()
+            && container
+                .workspace_name
+                .as_deref()
+                .map(|n| workspace_namespace(repo_hash, n))
+                != Some(namespace.clone())
+        {
+            continue;
+        }
+        let name = container
+            .workspace_name
+            .clone()
+            .or_else(|| known.map(|ws| ws.name.clone()));
+        let path = container
+            .workspace_path
+            .clone()
+            .or_else(|| known.map(|ws| ws.path.clone()));
+        let (Some(name), Some(path)) = (name, path) else {
+            skipped.push(container.name);
+            continue;
+        };
+
+        let known_entry = previous.iter().find(|ws| ws.name == name);
+        let created_at = known_entry
+            .map(|ws| ws.created_at.clone())
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+        let change_id = known_entry.and_then(|ws| ws.change_id.clone());
+
+        let entry = rebuilt
+            .entry(name.clone())
+            .or_insert_with(|| WorkspaceState {
+                name,
+                path,
+                namespace: namespace.clone(),
+                backend_type: backend_type.to_string(),
+                created_at,
+                change_id,
+                resources: vec![],
+            });
+        entry.resources.push(ResourceInfo {
+            service_name: service.clone(),
+            container_id: container.id,
+            image: container.image.unwrap_or_default(),
+            port_mappings,
+        });
+    }
+
+    for ws in previous {
+        if ws.resources.is_empty()
+            && Path::new(&ws.path).exists()
+            && !rebuilt.contains_key(&ws.name)
+        {
+            rebuilt.insert(ws.name.clone(), ws.clone());
+        }
+    }
+
+    let mut workspaces: Vec<WorkspaceState> = rebuilt.into_values().collect();
+    for ws in &mut workspaces {
+        ws.resources
+            .sort_by(|a, b| a.service_name.cmp(&b.service_name));
+    }
+
+    (workspaces, skipped)
+}
+
+fn same_workspace(a: &WorkspaceState, b: &WorkspaceState) -> bool {
+    let mut a_resources = a.resources.clone();
+    a_resources.sort_by(|x, y| x.service_name.cmp(&y.service_name));
+    let mut b_resources = b.resources.clone();
+    b_resources.sort_by(|x, y| x.service_name.cmp(&y.service_name));
+
+    a.path == b.path && a.namespace == b.namespace && a_resources == b_resources
+}
+
+pub async fn handle_state_repair(dry_run: bool, json: bool) -> Result<()> {
+    let repo_root = find_repo_root(Path::new("."))?;
+    let state_manager = StateManager::new(&repo_root);
+
+    let (previous, previous_readable) = match state_manager.list_workspaces() {
+        Ok(workspaces) => (workspaces, true),
+        Err(e) => {
+            if !json {
+                println!("⚠ {}", e);
+            }
+            (vec![], false)
+        }
+    };
+
+    let backend = DockerBackend::new()?;
+    let mut containers = Vec::new();
+    for resource in backend.list_managed().await? {
+        if resource.kind != ResourceKind::Container {
+            continue;
+        }
+        let ports = backend.inspect_ports(&resource.id).await?;
+        containers.push((resource, ports));
+    }
+
+    let (rebuilt, skipped) = reconstruct(
+        state_manager.repo_hash(),
+        backend.backend_type(),
+        containers,
+        &previous,
+    );
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for ws in &rebuilt {
+        match previous.iter().find(|p| p.name == ws.name) {
+            None => added.push(ws.name.clone()),
+            Some(p) if !same_workspace(p, ws) => changed.push(ws.name.clone()),
+            Some(_) => {}
+        }
+    }
+    let removed: Vec<String> = previous
+        .iter()
+        .filter(|p| !rebuilt.iter().any(|ws| ws.name == p.name))
+        .map(|p| p.name.clone())
+        .collect();
+
+    let unchanged =
+        previous_readable && added.is_empty() && changed.is_empty() && removed.is_empty();
+
+    let mut backup = None;
+    let status = if unchanged {
+        "unchanged"
+    } else if dry_run {
+        "dry_run"
+    } else {
+        backup = state_manager.replace_workspaces(rebuilt)?;
+        "repaired"
+    };
+
+    if json {
+        let output = StateRepairOutput {
+            status: status.to_string(),
+            previous_readable,
+            backup: backup.map(|p| p.to_string_lossy().to_string()),
+            added,
+            removed,
+            changed,
+            skipped,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    for name in &added {
+        println!("  + {}", name);
+    }
+    for name in &changed {
+        println!("  ~ {}", name);
+    }
+    for name in &removed {
+        println!("  - {}", name);
+    }
+    for name in &skipped {
+        println!("  ? {} (no workspace labels, left untouched)", name);
+    }
+
+    match status {
+        "unchanged" => println!("State registry already matches the backend."),
+        "dry_run" => println!("\n(Dry run - run without --dry-run to rewrite the registry)"),
+        _ => {
+            if let Some(path) = &backup {
+                println!("Previous registry saved to {}", path.display());
+            }
+            println!("✓ State registry rebuilt");
+        }
+    }
+
+    Ok(())
+}
+
+pub fn handle_state_migrate(dry_run: bool, json: bool) -> Result<()> {
+    let repo_root = find_repo_root(Path::new("."))?;
+    let state_manager = StateManager::new(&repo_root);
+
+    let (from_version, pending) = state_manager.pending_migrations()?;
+    let migrations: Vec<MigrationInfo> = pending
+        .iter()
+        .map(|m| MigrationInfo {
+            from: m.from.to_string(),
+            to: m.to.to_string(),
+            description: m.description.to_string(),
+        })
+        .collect();
+
+    let mut backup = None;
+    let status = if migrations.is_empty() {
+        "up_to_date"
+    } else if dry_run {
+        "dry_run"
+    } else {
+        backup = state_manager.migrate()?.1;
+        "migrated"
+    };
+
+    if json {
+        let output = StateMigrateOutput {
+            status: status.to_string(),
+            from_version,
+            to_version: STATE_VERSION.to_string(),
+            migrations,
+            backup: backup.map(|p| p.to_string_lossy().to_string()),
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if migrations.is_empty() {
+        println!("State registry is up to date (version {}).", from_version);
+        return Ok(());
+    }
+
+    println!(
+        "State registry version {} -> {}:",
+        from_version, STATE_VERSION
+    );
+    for m in &migrations {
+        println!("  {} -> {}: {}", m.from, m.to, m.description);
+    }
+
+    if dry_run {
+        println!("\n(Dry run - run without --dry-run to migrate)");
+    } else {
+        if let Some(path) = &backup {
+            println!("Previous registry saved to {}", path.display());
+        }
+        println!("✓ State registry migrated");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn container(
+        namespace: &str,
+        service: &str,
+        workspace: Option<(&str, &str)>,
+    ) -> ManagedResource {
+        ManagedResource {
+            kind: ResourceKind::Container,
+            id: format!("{}-{}-id", namespace, service),
+            name: format!("{}-{}", namespace, service),
+            namespace: Some(namespace.to_string()),
+            service: Some(service.to_string()),
+            repo: Some("aaaa".to_string()),
+            workspace_name: workspace.map(|(n, _)| n.to_string()),
+            workspace_path: workspace.map(|(_, p)| p.to_string()),
+            image: Some("postgres:15".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_reconstruct_from_labels() {
+        let containers = vec![
+            (
+                container("aether-aaaa-ws", "postgres", Some(("ws", "/tmp/ws"))),
+                HashMap::from([(5432, 32891)]),
+            ),
+            (
+                container("aether-aaaa-ws", "redis", Some(("ws", "/tmp/ws"))),
+                HashMap::from([(6379, 32892)]),
+            ),
+            (container("aether-aaaa-old", "redis", None), HashMap::new()),
+        ];
+
+        let (rebuilt, skipped) = reconstruct("aaaa", "docker", containers, &[]);
+        assert_eq!(rebuilt.len(), 1);
+        assert_eq!(rebuilt[0].name, "ws");
+        assert_eq!(rebuilt[0].resources.len(), 2);
+        assert_eq!(
+            rebuilt[0].resources[0].port_mappings.get(&5432),
+            Some(&32891)
+        );
+        assert_eq!(skipped, vec!["aether-aaaa-old-redis".to_string()]);
+    }
+
+    #[test]
+    fn test_reconstruct_uses_previous_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let previous = vec![
+            WorkspaceState {
+                name: "old".to_string(),
+                path: "/tmp/old".to_string(),
+                namespace: "aether-aaaa-old".to_string(),
+                backend_type: "docker".to_string(),
+                created_at: "2026-01-28T00:00:00Z".to_string(),
+                change_id: None,
+                resources: vec![],
+            },
+            WorkspaceState {
+                name: "empty".to_string(),
+                path: temp_dir.path().to_string_lossy().to_string(),
+                namespace: "aether-aaaa-empty".to_string(),
+                backend_type: "docker".to_string(),
+                created_at: "2026-01-28T00:00:00Z".to_string(),
+                change_id: None,
+                resources: vec![],
+            },
+        ];
+        let containers = vec![(container("aether-aaaa-old", "redis", None), HashMap::new())];
+
+        let (rebuilt, skipped) = reconstruct("aaaa", "docker", containers, &previous);
+        assert!(skipped.is_empty());
+        assert_eq!(rebuilt.len(), 2);
+        let old = rebuilt.iter().find(|ws| ws.name == "old").unwrap();
+        assert_eq!(old.created_at, "2026-01-28T00:00:00Z");
+        assert_eq!(old.resources.len(), 1);
+    }
+
+    #[test]
+    fn test_reconstruct_unlabeled_repo() {
+        let unlabeled = |namespace: &str, name: &str| {
+            let mut c = container(namespace, "redis", Some((name, "/tmp/ws")));
+            c.repo = None;
+            (c, HashMap::new())
+        };
+        let containers = vec![
+            unlabeled(&workspace_namespace("aaaa", "ws"), "ws"),
+            unlabeled(&workspace_namespace("bbbb", "other"), "other"),
+        ];
+
+        let (rebuilt, skipped) = reconstruct("aaaa", "docker", containers, &[]);
+        assert!(skipped.is_empty());
+        assert_eq!(rebuilt.len(), 1);
+        assert_eq!(rebuilt[0].name, "ws");
+        assert_eq!(rebuilt[0].resources.len(), 1);
+    }
+}
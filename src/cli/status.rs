@@ -1,73 +1,80 @@
+use super::{detect_drift, print_drift_notice, resolve_workspace};
 use crate::backend::{Backend, DockerBackend};
 use crate::error::Result;
-use crate::jj::JjCommand;
+use crate::jj::{query, JjCommand};
 use crate::output::json::{ContainerStatus, StatusOutput};
 use crate::provisioner::StateManager;
 use crate::repo::find_repo_root;
 use std::path::Path;
 
-pub async fn handle_status(json: bool) -> Result<()> {
-    // 1. Run jj status
-    let jj_output = JjCommand::status().execute();
+pub async fn handle_status(workspace: Option<&str>, json: bool) -> Result<()> {
+    // 1. Try to get workspace state
+    let repo_root = find_repo_root(Path::new("."));
+    let workspace_state = match &repo_root {
+        Ok(root) => resolve_workspace(&StateManager::new(root), workspace)?,
+        Err(_) => None,
+    };
+    let drift = repo_root.as_deref().ok().and_then(detect_drift);
+
+    // 2. Run jj status, in the selected workspace if one was given
+    let jj_dir = match (workspace, &workspace_state) {
+        (Some(_), Some(ws)) => Path::new(&ws.path),
+        _ => Path::new("."),
+    };
+    let jj_output = JjCommand::status().in_dir(jj_dir).execute();
+    let working_copy = query::working_copy(jj_dir).ok();
     let jj_status_text = match &jj_output {
         Ok(output) => Some(output.stdout.clone()),
         Err(_) => None,
     };
 
-    // 2. Try to get workspace state
-    let repo_root = find_repo_root(Path::new("."));
-
-    if let Ok(root) = repo_root {
-        let state_manager = StateManager::new(&root);
-        let current_dir = std::env::current_dir()?;
-        let workspace_name = current_dir
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
-
-        if let Some(workspace_state) = state_manager.get_workspace(workspace_name)? {
-            let backend = DockerBackend::new()?;
-            let resources = backend.status(&workspace_state.namespace).await?;
+    if let Some(workspace_state) = workspace_state {
+        let backend = DockerBackend::new()?;
+        let resources = backend.status(&workspace_state.namespace).await?;
 
-            if json {
-                let output = StatusOutput {
-                    status: "ok".to_string(),
-                    workspace: Some(workspace_name.to_string()),
-                    namespace: Some(workspace_state.namespace.clone()),
-                    backend: Some(workspace_state.backend_type.clone()),
-                    resources: resources
-                        .iter()
-                        .map(|r| ContainerStatus {
-                            service_name: r.service_name.clone(),
-                            container_id: r.container_id.clone(),
-                            status: r.status.clone(),
-                            port_mappings: r.port_mappings.clone(),
-                        })
-                        .collect(),
-                    jj_status: jj_status_text,
-                };
-                println!("{}", serde_json::to_string_pretty(&output)?);
-            } else {
-                if let Some(text) = &jj_output.ok().map(|o| o.stdout) {
-                    print!("{}", text);
-                }
-                println!("\n=== Infrastructure Status ===");
-                println!("Namespace: {}", workspace_state.namespace);
-                println!("Backend: {}", workspace_state.backend_type);
+        if json {
+            let output = StatusOutput {
+                status: "ok".to_string(),
+                workspace: Some(workspace_state.name.clone()),
+                namespace: Some(workspace_state.namespace.clone()),
+                backend: Some(workspace_state.backend_type.clone()),
+                resources: resources
+                    .iter()
+                    .map(|r| ContainerStatus {
+                        service_name: r.service_name.clone(),
+                        container_id: r.container_id.clone(),
+                        status: r.status.clone(),
+                        port_mappings: r.port_mappings.clone(),
+                    })
+                    .collect(),
+                working_copy,
+                jj_status: jj_status_text,
+                drift,
+            };
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            if let Some(text) = &jj_output.ok().map(|o| o.stdout) {
+                print!("{}", text);
+            }
+            println!("\n=== Infrastructure Status ===");
+            println!("Namespace: {}", workspace_state.namespace);
+            println!("Backend: {}", workspace_state.backend_type);
 
-                for resource in resources {
-                    let short_id = &resource.container_id[..12.min(resource.container_id.len())];
-                    println!(
-                        "  {} [{}]: {}",
-                        resource.service_name, short_id, resource.status
-                    );
-                    for (internal, external) in &resource.port_mappings {
-                        println!("    port {} -> {}", internal, external);
-                    }
+            for resource in resources {
+                let short_id = &resource.container_id[..12.min(resource.container_id.len())];
+                println!(
+                    "  {} [{}]: {}",
+                    resource.service_name, short_id, resource.status
+                );
+                for (internal, external) in &resource.port_mappings {
+                    println!("    port {} -> {}", internal, external);
                 }
             }
-            return Ok(());
+            if let Some(drift) = &drift {
+                print_drift_notice(drift);
+            }
         }
+        return Ok(());
     }
 
     // No workspace state found
@@ -78,7 +85,9 @@ pub async fn handle_status(json: bool) -> Result<()> {
             namespace: None,
             backend: None,
             resources: vec![],
+            working_copy,
             jj_status: jj_status_text,
+            drift,
         };
         println!("{}", serde_json::to_string_pretty(&output)?);
     } else {
@@ -86,6 +95,9 @@ pub async fn handle_status(json: bool) -> Result<()> {
             print!("{}", output.stdout);
         }
         println!("\n(No Aether infrastructure in current workspace)");
+        if let Some(drift) = &drift {
+            print_drift_notice(drift);
+        }
     }
 
     Ok(())
@@ -0,0 +1,172 @@
+use crate::backend::{Backend, DockerBackend};
+use crate::error::Result;
+use crate::output::json::{DriftReport, SyncOutput};
+use crate::provisioner::{StateManager, WorkspaceState};
+use crate::repo::find_repo_root;
+use crate::vcs::{detect_vcs, VcsWorkspace};
+use std::path::Path;
+
+fn compute_drift(vcs_workspaces: &[VcsWorkspace], registered: &[WorkspaceState]) -> DriftReport {
+    let mut unknown_to_jj: Vec<String> = registered
+        .iter()
+        .filter(|ws| !vcs_workspaces.iter().any(|v| v.name == ws.name))
+        .map(|ws| ws.name.clone())
+        .collect();
+    let mut without_infra: Vec<String> = vcs_workspaces
+        .iter()
+        .filter(|v| !registered.iter().any(|ws| ws.name == v.name))
+        .map(|v| v.name.clone())
+        .collect();
+    // The VCS keeps listing a workspace whose directory was deleted
+    let mut missing_directory: Vec<String> = registered
+        .iter()
+        .filter(|ws| !unknown_to_jj.contains(&ws.name) && !Path::new(&ws.path).exists())
+        .map(|ws| ws.name.clone())
+        .collect();
+
+    unknown_to_jj.sort();
+    without_infra.sort();
+    missing_directory.sort();
+
+    DriftReport {
+        unknown_to_jj,
+        without_infra,
+        missing_directory,
+    }
+}
+
+/// Drift for `list` and `status`, which report it in passing. Returns `None`
+/// whenever it cannot be determined, e.g. when jj or git is not installed.
+pub fn detect_drift(repo_root: &Path) -> Option<DriftReport> {
+    let vcs_workspaces = detect_vcs(repo_root).ok()?.list_workspaces().ok()?;
+    let registered = StateManager::new(repo_root).list_workspaces().ok()?;
+    Some(compute_drift(&vcs_workspaces, &registered))
+}
+
+/// Print a short drift notice after human-readable `list`/`status` output.
+pub fn print_drift_notice(drift: &DriftReport) {
+    for name in &drift.unknown_to_jj {
+        println!(
+            "⚠ '{}' has infrastructure but is no longer a workspace of the repository (run `ajj sync`)",
+            name
+        );
+    }
+    for name in &drift.missing_directory {
+        println!(
+            "⚠ '{}' has infrastructure but its directory was deleted (run `ajj workspace forget {}`)",
+            name, name
+        );
+    }
+}
+
+pub async fn handle_sync(force: bool, json: bool) -> Result<()> {
+    let repo_root = find_repo_root(Path::new("."))?;
+    let state_manager = StateManager::new(&repo_root);
+
+    let vcs_workspaces = detect_vcs(&repo_root)?.list_workspaces()?;
+    let registered = state_manager.list_workspaces()?;
+    let drift = compute_drift(&vcs_workspaces, &registered);
+
+    if !json {
+        if drift.is_empty() {
+            println!("Registry matches the repository's workspaces.");
+        }
+        for name in &drift.unknown_to_jj {
+            println!(
+                "  - {}: infrastructure for a workspace the VCS no longer knows",
+                name
+            );
+        }
+        for name in &drift.without_infra {
+            println!(
+                "  + {}: workspace without infrastructure (`ajj workspace attach`)",
+                name
+            );
+        }
+        for name in &drift.missing_directory {
+            println!(
+                "  ! {}: directory deleted (`ajj workspace forget {}`)",
+                name, name
+            );
+        }
+    }
+
+    let mut deprovisioned = Vec::new();
+    if force && !drift.unknown_to_jj.is_empty() {
+        let backend = DockerBackend::new()?;
+        for ws in registered
+            .iter()
+            .filter(|ws| drift.unknown_to_jj.contains(&ws.name))
+        {
+            backend.deprovision(&ws.namespace).await?;
+            state_manager.unregister_workspace(&ws.name)?;
+            deprovisioned.push(ws.name.clone());
+            if !json {
+                println!("  Deprovisioned: {}", ws.name);
+            }
+        }
+    }
+
+    let status = if drift.unknown_to_jj.is_empty() {
+        "in_sync"
+    } else if force {
+        "synced"
+    } else {
+        "dry_run"
+    };
+
+    if json {
+        let output = SyncOutput {
+            status: status.to_string(),
+            drift,
+            deprovisioned,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if status == "dry_run" {
+        println!("\n(Dry run - use --force to deprovision infrastructure the VCS no longer knows)");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn registered(name: &str, path: &Path) -> WorkspaceState {
+        WorkspaceState {
+            name: name.to_string(),
+            path: path.to_string_lossy().to_string(),
+            namespace: format!("aether-test-{}", name),
+            backend_type: "docker".to_string(),
+            created_at: "2026-01-28T00:00:00Z".to_string(),
+            change_id: None,
+            resources: vec![],
+        }
+    }
+
+    #[test]
+    fn test_compute_drift() {
+        let vcs_workspaces = crate::vcs::parse_worktree_list(
+            "worktree /work/repo\nHEAD 230dd059\n\nworktree /work/feature-x\nHEAD 3d4e5f60\n",
+        );
+        let temp_dir = TempDir::new().unwrap();
+        let states = vec![
+            registered("feature-x", temp_dir.path()),
+            registered("gone", &temp_dir.path().join("gone")),
+        ];
+
+        let drift = compute_drift(&vcs_workspaces, &states);
+        assert_eq!(drift.unknown_to_jj, vec!["gone".to_string()]);
+        assert_eq!(drift.without_infra, vec!["default".to_string()]);
+        assert!(drift.missing_directory.is_empty());
+        assert!(!drift.is_empty());
+
+        // Still a workspace of the repository, but deleted from disk
+        let states = vec![registered("feature-x", &temp_dir.path().join("deleted"))];
+        let drift = compute_drift(&vcs_workspaces, &states);
+        assert!(drift.unknown_to_jj.is_empty());
+        assert_eq!(drift.missing_directory, vec!["feature-x".to_string()]);
+    }
+}
@@ -0,0 +1,88 @@
+use super::workspace::{load_workspace_config, provision_workspace, workspace_info};
+use crate::backend::{Backend, DockerBackend};
+use crate::error::{AetherError, Result};
+use crate::jj::{query, JjCommand};
+use crate::output::json::UndoOutput;
+use crate::provisioner::{OperationKind, StateManager};
+use crate::repo::{detect_vcs_kind, find_repo_root, VcsKind};
+use std::path::Path;
+
+/// Run `jj undo` and reverse the infrastructure change ajj made in the undone
+/// operation: tear down an added workspace or re-provision a forgotten one.
+pub async fn handle_undo(
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    if detect_vcs_kind(Path::new("."))? != VcsKind::Jj {
+        return Err(AetherError::Config(
+            "`ajj undo` needs jj's operation log; git repositories have none".into(),
+        ));
+    }
+    let repo_root = find_repo_root(Path::new("."))?;
+    let state_manager = StateManager::new(&repo_root);
+
+    // 1. Find what ajj did in the operation about to be undone
+    let operation = query::undo_target(Path::new("."))?;
+    let record = state_manager.find_operation(&operation.id)?;
+
+    // 2. Undo it in jj first; if that fails, the infrastructure stays as is
+    let output = JjCommand::undo().execute()?;
+    if !json {
+        eprint!("{}", output.stderr);
+    }
+
+    // 3. Reverse the infrastructure side
+    let mut workspace = None;
+    if let Some(record) = &record {
+        let ws = &record.workspace;
+        match record.kind {
+            OperationKind::WorkspaceAdd => {
+                if let Some(current) = state_manager.get_workspace(&ws.name)? {
+                    let backend = DockerBackend::new()?;
+                    backend.deprovision(&current.namespace).await?;
+                    state_manager.unregister_workspace(&ws.name)?;
+                }
+                if !json {
+                    println!("✓ Tore down infrastructure of '{}'", ws.name);
+                    println!("  (the directory {} is left on disk)", ws.path);
+                }
+            }
+            OperationKind::WorkspaceForget => {
+                let config = match &record.config {
+                    Some(config) => config.clone(),
+                    None => load_workspace_config(config_path, profile, Path::new(&ws.path))?,
+                };
+                let state = provision_workspace(
+                    &config,
+                    &state_manager,
+                    &repo_root,
+                    &ws.name,
+                    Path::new(&ws.path),
+                )
+                .await?;
+                if !json {
+                    println!(
+                        "✓ Re-provisioned '{}' with {} containers",
+                        state.name,
+                        state.resources.len()
+                    );
+                }
+                workspace = Some(state);
+            }
+        }
+        state_manager.remove_operation(&operation.id)?;
+    }
+
+    if json {
+        let output = UndoOutput {
+            status: "undone".to_string(),
+            operation_id: operation.id,
+            reversed: record.map(|r| r.kind.as_str().to_string()),
+            workspace: workspace.as_ref().map(workspace_info),
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    }
+
+    Ok(())
+}
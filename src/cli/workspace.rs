@@ -1,135 +1,239 @@
-use crate::backend::{Backend, DockerBackend, ServiceSpec};
-use crate::config::{find_config, load_config};
+use super::resolve_workspace;
+use super::run::load_env_file;
+use crate::backend::{
+    Backend, DockerBackend, HealthcheckSpec, ResourceOwner, RestartSpec, ServiceSpec,
+};
+use crate::config::{
+    diagnostics_error, find_config, find_preset, global_config_path, load_config, load_resolved,
+    local_config_path, parse_duration, parse_memory_to_bytes, parse_restart, resolve_config,
+    resolve_layers, validate_resolved, AetherConfig, HealthcheckConfig, ServiceConfig,
+};
 use crate::error::Result;
-use crate::jj::JjCommand;
+use crate::jj::query;
 use crate::output::json::{AjjOutput, ResourceDetail, WorkspaceInfo};
 use crate::provisioner::{
-    context_injector, ContextInjector, PortAllocator, ResourceInfo, StateManager, WorkspaceState,
+    context_injector, ContextInjector, OperationKind, OperationRecord, PortAllocator, RepoIndex,
+    ResourceInfo, StateManager, WorkspaceState,
 };
-use crate::repo::find_repo_root;
+use crate::repo::{find_repo_root, find_workspace_root, VcsKind};
+use crate::vcs::{detect_vcs, Vcs, VcsWorkspace};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-/// Parse memory string (e.g., "512m", "1g") to bytes
-fn parse_memory_to_bytes(mem: &str) -> Result<i64> {
-    let mem = mem.trim().to_lowercase();
-    let (num_str, unit) = if mem.ends_with('b') {
-        (&mem[..mem.len() - 1], &mem[mem.len() - 2..])
-    } else {
-        (&mem[..mem.len() - 1], &mem[mem.len() - 1..])
-    };
+fn healthcheck_spec(healthcheck: &HealthcheckConfig) -> Result<HealthcheckSpec> {
+    let duration = |d: &Option<String>| d.as_deref().map(parse_duration).transpose();
+    Ok(HealthcheckSpec {
+        test: healthcheck.test.clone(),
+        interval: duration(&healthcheck.interval)?,
+        timeout: duration(&healthcheck.timeout)?,
+        retries: healthcheck.retries,
+        start_period: duration(&healthcheck.start_period)?,
+    })
+}
+
+/// `env_file` of a service in the workspace at `workspace_root`: there, or
+/// else at the same place in the main checkout, since ignored files such as
+/// `.env` are not part of new workspaces.
+fn env_file_path(workspace_root: &Path, env_file: &str) -> Option<PathBuf> {
+    let path = workspace_root.join(env_file);
+    if path.is_file() {
+        return Some(path);
+    }
+    let main = find_repo_root(workspace_root).ok()?.join(env_file);
+    main.is_file().then_some(main)
+}
+
+/// The container to run for service `name`, publishing its ports on the
+/// host ports in `port_mappings` (container port -> host port). `env_file`
+/// paths are relative to `workspace_root`.
+pub(super) fn service_spec(
+    name: &str,
+    svc_config: &ServiceConfig,
+    port_mappings: HashMap<u16, u16>,
+    workspace_root: &Path,
+) -> Result<ServiceSpec> {
+    let ports = svc_config
+        .ports
+        .iter()
+        .map(|port_str| {
+            port_str.parse().map_err(|_| {
+                crate::error::AetherError::Config(format!("Invalid port: {}", port_str))
+            })
+        })
+        .collect::<Result<Vec<u16>>>()?;
 
-    let num: f64 = num_str
-        .parse()
-        .map_err(|_| crate::error::AetherError::Config(format!("Invalid memory value: {}", mem)))?;
-
-    let multiplier = match unit {
-        "k" | "kb" => 1024.0,
-        "m" | "mb" => 1024.0 * 1024.0,
-        "g" | "gb" => 1024.0 * 1024.0 * 1024.0,
-        _ => {
-            return Err(crate::error::AetherError::Config(format!(
-                "Invalid memory unit: {}",
-                unit
-            )))
+    let resources = svc_config.resources.as_ref();
+    let memory_limit = resources
+        .and_then(|r| r.memory_limit.as_deref())
+        .map(parse_memory_to_bytes)
+        .transpose()?;
+    let memory_reservation = resources
+        .and_then(|r| r.memory_reservation.as_deref())
+        .map(parse_memory_to_bytes)
+        .transpose()?;
+
+    let mut env = HashMap::new();
+    for env_file in &svc_config.env_file {
+        let path = env_file_path(workspace_root, env_file).ok_or_else(|| {
+            crate::error::AetherError::Config(format!(
+                "env_file {} of service '{}' not found in {} or the main checkout",
+                env_file,
+                name,
+                workspace_root.display()
+            ))
+        })?;
+        env.extend(load_env_file(&path)?);
+    }
+    env.extend(svc_config.env.clone());
+    if let Some(preset) = svc_config.preset.as_deref().and_then(find_preset) {
+        for (key, value) in preset.runtime_env(&port_mappings) {
+            env.entry(key).or_insert(value);
         }
-    };
+    }
+    let healthcheck = svc_config
+        .healthcheck
+        .as_ref()
+        .map(healthcheck_spec)
+        .transpose()?;
 
-    Ok((num * multiplier) as i64)
+    Ok(ServiceSpec {
+        name: name.to_string(),
+        image: svc_config.image.clone(),
+        ports,
+        env,
+        volumes: svc_config
+            .volumes
+            .iter()
+            .map(|volume| resolve_bind(workspace_root, volume))
+            .collect(),
+        command: svc_config.command.clone(),
+        port_mappings,
+        depends_on: svc_config.depends_on.clone(),
+        cpu_limit: resources.and_then(|r| r.cpu_limit),
+        cpu_reservation: resources.and_then(|r| r.cpu_reservation),
+        memory_limit,
+        memory_reservation,
+        healthcheck,
+        entrypoint: svc_config.entrypoint.clone(),
+        working_dir: svc_config.working_dir.clone(),
+        user: svc_config.user.clone(),
+        hostname: svc_config.hostname.clone(),
+        extra_hosts: svc_config.extra_hosts.clone(),
+        tmpfs: svc_config.tmpfs.clone(),
+        shm_size: svc_config
+            .shm_size
+            .as_deref()
+            .map(parse_memory_to_bytes)
+            .transpose()?,
+        ulimits: svc_config
+            .ulimits
+            .iter()
+            .map(|(name, ulimit)| (name.clone(), ulimit.limits()))
+            .collect(),
+        cap_add: svc_config.cap_add.clone(),
+        cap_drop: svc_config.cap_drop.clone(),
+        labels: svc_config.labels.clone(),
+        restart: svc_config
+            .restart
+            .as_deref()
+            .map(parse_restart)
+            .transpose()?
+            .map(|(policy, max_retries)| RestartSpec {
+                policy: policy.to_string(),
+                max_retries,
+            }),
+        stop_grace_period: svc_config
+            .stop_grace_period
+            .as_deref()
+            .map(parse_duration)
+            .transpose()?,
+    })
 }
 
-pub async fn handle_workspace_add(
-    destination: &str,
-    revision: Option<&str>,
-    config_path: Option<&str>,
-    json: bool,
-) -> Result<()> {
-    // 1. Find and load config
-    let config_file = if let Some(path) = config_path {
-        std::path::PathBuf::from(path)
-    } else {
-        find_config(Path::new("."))?
-    };
-    let config = load_config(&config_file)?;
+/// `volume` with a relative bind source (`./init:/docker-entrypoint-initdb.d`)
+/// made absolute against the workspace root, the way compose resolves it
+/// against the project directory. Docker would reject the relative path.
+fn resolve_bind(workspace_root: &Path, volume: &str) -> String {
+    match volume.split_once(':') {
+        Some((source, rest))
+            if source == "." || source.starts_with("./") || source.starts_with("../") =>
+        {
+            let root = workspace_root
+                .canonicalize()
+                .unwrap_or_else(|_| workspace_root.to_path_buf());
+            let source: PathBuf = root
+                .join(source)
+                .components()
+                .filter(|c| *c != std::path::Component::CurDir)
+                .collect();
+            format!("{}:{}", source.display(), rest)
+        }
+        _ => volume.to_string(),
+    }
+}
 
-    // 2. Execute jj workspace add
-    let jj_cmd = JjCommand::workspace_add(destination, revision);
-    jj_cmd.execute()?;
+/// Provision infrastructure for the jj workspace rooted at `workspace_root`,
+/// inject its context file and register it. Shared by `workspace add` and
+/// `workspace attach`.
+pub(super) async fn provision_workspace(
+    config: &AetherConfig,
+    state_manager: &StateManager,
+    repo_root: &Path,
+    workspace_name: &str,
+    workspace_root: &Path,
+) -> Result<WorkspaceState> {
+    let namespace = state_manager.namespace_for(workspace_name);
 
-    // 3. Generate namespace
-    let workspace_name = Path::new(destination)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| crate::error::AetherError::Config("Invalid destination".into()))?;
-    let namespace = format!("aether-{}", workspace_name);
-
-    // 4. Count total ports needed
+    // 1. Count total ports needed
     let total_ports: usize = config.services.values().map(|s| s.ports.len()).sum();
 
-    // 5. Allocate ports
+    // 2. Allocate ports
     let allocator = PortAllocator::new();
     let allocated_ports = allocator.allocate(total_ports)?;
 
-    // 6. Build service specs with port mappings
+    // 3. Build service specs with port mappings
     let mut services = HashMap::new();
     let mut port_idx = 0;
 
     for (name, svc_config) in &config.services {
         let mut port_mappings = HashMap::new();
-
         for port_str in &svc_config.ports {
             let internal_port: u16 = port_str.parse().map_err(|_| {
                 crate::error::AetherError::Config(format!("Invalid port: {}", port_str))
             })?;
-
             port_mappings.insert(internal_port, allocated_ports[port_idx]);
             port_idx += 1;
         }
 
-        services.insert(
-            name.clone(),
-            ServiceSpec {
-                name: name.clone(),
-                image: svc_config.image.clone(),
-                ports: svc_config
-                    .ports
-                    .iter()
-                    .filter_map(|p| p.parse().ok())
-                    .collect(),
-                env: svc_config.env.clone(),
-                volumes: svc_config.volumes.clone(),
-                command: svc_config.command.clone(),
-                port_mappings,
-                depends_on: svc_config.depends_on.clone(),
-                cpu_limit: svc_config.resources.as_ref().and_then(|r| r.cpu_limit),
-                cpu_reservation: svc_config
-                    .resources
-                    .as_ref()
-                    .and_then(|r| r.cpu_reservation),
-                memory_limit: svc_config
-                    .resources
-                    .as_ref()
-                    .and_then(|r| r.memory_limit.as_ref())
-                    .and_then(|m| parse_memory_to_bytes(m).ok()),
-                memory_reservation: svc_config
-                    .resources
-                    .as_ref()
-                    .and_then(|r| r.memory_reservation.as_ref())
-                    .and_then(|m| parse_memory_to_bytes(m).ok()),
-            },
-        );
+        let spec = service_spec(name, svc_config, port_mappings, workspace_root)?;
+        services.insert(name.clone(), spec);
     }
 
-    // 7. Provision via backend
+    // 4. Provision via backend
+    let workspace_path = workspace_root.canonicalize()?.to_string_lossy().to_string();
+    let owner = ResourceOwner {
+        repo_id: state_manager.repo_hash().to_string(),
+        workspace_name: workspace_name.to_string(),
+        workspace_path: workspace_path.clone(),
+    };
     let backend = DockerBackend::new()?;
-    let handles = backend.provision(&namespace, &services).await?;
+    let handles = backend.provision(&namespace, &owner, &services).await?;
 
-    // 8. Inject context if configured
+    // 5. Inject context if configured
     if let Some(injection_config) = &config.injection {
         let injector = ContextInjector::new();
         let mut resources = HashMap::new();
 
         for handle in &handles {
+            let preset = config
+                .services
+                .get(&handle.service_name)
+                .and_then(|s| s.preset.as_deref())
+                .and_then(find_preset);
+            let values = match (preset, services.get(&handle.service_name)) {
+                (Some(preset), Some(spec)) => preset.values(&spec.env, &handle.port_mappings),
+                _ => Default::default(),
+            };
             resources.insert(
                 handle.service_name.clone(),
                 context_injector::ResourceHandle {
@@ -137,27 +241,26 @@ pub async fn handle_workspace_add(
                     container_id: handle.container_id.clone(),
                     image: handle.image.clone(),
                     port_mappings: handle.port_mappings.clone(),
+                    values,
                 },
             );
         }
 
         let rendered = injector.render(&injection_config.template, &resources)?;
-        let dest_path = Path::new(destination).join(&injection_config.file);
+        let dest_path = workspace_root.join(&injection_config.file);
         std::fs::write(dest_path, rendered)?;
     }
 
-    // 9. Register workspace
-    let repo_root = find_repo_root(Path::new("."))?;
-    let state_manager = StateManager::new(&repo_root);
-
+    // 6. Register workspace
     let workspace_state = WorkspaceState {
         name: workspace_name.to_string(),
-        path: std::fs::canonicalize(destination)?
-            .to_string_lossy()
-            .to_string(),
-        namespace: namespace.clone(),
-        backend_type: "docker".to_string(),
+        path: workspace_path,
+        namespace,
+        backend_type: backend.backend_type().to_string(),
         created_at: chrono::Utc::now().to_rfc3339(),
+        change_id: query::last_snapshot(workspace_root)
+            .ok()
+            .map(|commit| commit.change_id),
         resources: handles
             .iter()
             .map(|h| ResourceInfo {
@@ -169,69 +272,485 @@ pub async fn handle_workspace_add(
             .collect(),
     };
 
-    state_manager.register_workspace(workspace_state)?;
+    state_manager.register_workspace(workspace_state.clone())?;
+    if let Some(index) = RepoIndex::user_default() {
+        // Only needed for `cleanup --all-repos`; never fail provisioning over it
+        let _ = index.record(state_manager.repo_hash(), repo_root);
+    }
+
+    Ok(workspace_state)
+}
+
+pub(super) fn workspace_info(state: &WorkspaceState) -> WorkspaceInfo {
+    WorkspaceInfo {
+        name: state.name.clone(),
+        root: state.path.clone(),
+        backend: state.backend_type.clone(),
+        namespace: state.namespace.clone(),
+        change_id: state.change_id.clone(),
+        resources: state
+            .resources
+            .iter()
+            .map(|r| ResourceDetail {
+                service_name: r.service_name.clone(),
+                container_id: r.container_id.clone(),
+                image: r.image.clone(),
+                port_mappings: r.port_mappings.clone(),
+            })
+            .collect(),
+    }
+}
+
+pub(super) fn load_workspace_config(
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    search_from: &Path,
+) -> Result<AetherConfig> {
+    let config_file = if let Some(path) = config_path {
+        std::path::PathBuf::from(path)
+    } else {
+        find_config(search_from)?
+    };
+    load_config(&config_file, profile)
+}
+
+pub async fn handle_workspace_add(
+    destination: &str,
+    revision: Option<&str>,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let workspace_name = Path::new(destination)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| crate::error::AetherError::Config("Invalid destination".into()))?;
+
+    // 1. Validate the config the workspace will get before creating anything
+    let vcs = detect_vcs(Path::new("."))?;
+    validate_before_add(vcs.as_ref(), revision, config_path, profile)?;
+
+    // 2. Create the jj workspace or git worktree
+    vcs.add_workspace(destination, revision)?;
+    let operation = vcs.current_operation();
 
-    // 10. Output
+    // 3. Load config from the new checkout, so the workspace gets the
+    //    infrastructure its revision expects
+    let config =
+        load_workspace_config(config_path, profile, Path::new(destination)).map_err(|e| {
+            crate::error::AetherError::Config(format!(
+                "Workspace '{}' was created but its revision has no usable aether.toml ({}). \
+             Add one and run `ajj workspace attach {}`",
+                workspace_name, e, destination
+            ))
+        })?;
+
+    // 4. Provision and register infrastructure
+    let repo_root = find_repo_root(Path::new("."))?;
+    let state_manager = StateManager::new(&repo_root);
+    let state = provision_workspace(
+        &config,
+        &state_manager,
+        &repo_root,
+        workspace_name,
+        Path::new(destination),
+    )
+    .await?;
+    let _ = journal(
+        &state_manager,
+        operation,
+        OperationKind::WorkspaceAdd,
+        &state,
+        None,
+    );
+
+    // 5. Output
     if json {
         let output = AjjOutput {
             status: "ready".to_string(),
             operation: "workspace_add".to_string(),
-            workspace: Some(WorkspaceInfo {
-                name: workspace_name.to_string(),
-                root: std::fs::canonicalize(destination)?
-                    .to_string_lossy()
-                    .to_string(),
-                backend: "docker".to_string(),
-                namespace: namespace.clone(),
-                resources: handles
-                    .iter()
-                    .map(|h| ResourceDetail {
-                        service_name: h.service_name.clone(),
-                        container_id: h.container_id.clone(),
-                        image: h.image.clone(),
-                        port_mappings: h.port_mappings.clone(),
-                    })
-                    .collect(),
-            }),
+            workspace: Some(workspace_info(&state)),
             errors: vec![],
         };
         println!("{}", serde_json::to_string_pretty(&output)?);
     } else {
         println!(
             "✓ Workspace '{}' created with {} containers",
-            workspace_name,
-            handles.len()
+            state.name,
+            state.resources.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Validate the config `workspace add` is about to provision from: the
+/// `--config` file if given, otherwise `aether.toml` at `revision`, or the
+/// current checkout's when no revision is given. For a revision, includes
+/// and `aether.local.toml` are read from the current checkout. Also checks
+/// that every `env_file` will be found, since a missing one would only fail
+/// after the checkout was created.
+fn validate_before_add(
+    vcs: &dyn Vcs,
+    revision: Option<&str>,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+) -> Result<()> {
+    let resolved = match (config_path, revision) {
+        (Some(path), _) => resolve_config(Path::new(path), profile)?,
+        (None, Some(revision)) => {
+            let source = vcs.show_file(revision, "aether.toml").map_err(|e| {
+                crate::error::AetherError::Config(format!(
+                    "No aether.toml at revision '{}': {}",
+                    revision,
+                    e.to_string().trim_end()
+                ))
+            })?;
+            let checkout = find_workspace_root(Path::new("."))?;
+            resolve_layers(
+                Path::new(&format!("{}:aether.toml", revision)),
+                source,
+                &checkout,
+                global_config_path().as_deref(),
+                local_config_path(&checkout.join("aether.toml")).as_deref(),
+                profile,
+            )?
+        }
+        (None, None) => resolve_config(&find_config(Path::new("."))?, profile)?,
+    };
+
+    let diagnostics = validate_resolved(&resolved);
+    if !diagnostics.is_empty() {
+        return Err(diagnostics_error(&diagnostics));
+    }
+
+    // An env_file must come with the revision or, like an ignored `.env`,
+    // be found in the current or main checkout
+    let config = load_resolved(&resolved)?;
+    let checkout = find_workspace_root(Path::new("."))?;
+    for (name, service) in &config.services {
+        for env_file in &service.env_file {
+            let in_revision =
+                revision.is_some_and(|revision| vcs.show_file(revision, env_file).is_ok());
+            if !in_revision && env_file_path(&checkout, env_file).is_none() {
+                return Err(crate::error::AetherError::Config(format!(
+                    "env_file {} of service '{}' not found in {} or the main checkout",
+                    env_file,
+                    name,
+                    checkout.display()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Journal a workspace change made in jj operation `operation` so `ajj undo`
+/// can reverse it. Best-effort: without an operation id (e.g. in git repos)
+/// there is nothing to match against, and failing to journal never fails the
+/// command. Returns whether the change was journaled.
+fn journal(
+    state_manager: &StateManager,
+    operation: Option<String>,
+    kind: OperationKind,
+    workspace: &WorkspaceState,
+    config: Option<AetherConfig>,
+) -> bool {
+    let Some(operation) = operation else {
+        return false;
+    };
+    state_manager
+        .record_operation(OperationRecord {
+            operation_id: operation,
+            kind,
+            workspace: workspace.clone(),
+            config,
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+        })
+        .is_ok()
+}
+
+/// Name jj gives the workspace rooted at `workspace_root`: `default` for the
+/// main checkout, otherwise the directory name `jj workspace add` uses.
+fn default_workspace_name(repo_root: &Path, workspace_root: &Path) -> Result<String> {
+    if workspace_root == repo_root {
+        return Ok("default".to_string());
+    }
+
+    workspace_root
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| crate::error::AetherError::Config("Invalid workspace path".into()))
+}
+
+/// Whether `name` is what the VCS calls the workspace at `workspace_root`.
+/// Registry entries are matched to VCS workspaces by name, so any other
+/// name would look like drift to `ajj sync`.
+fn is_vcs_workspace(workspaces: &[VcsWorkspace], name: &str, workspace_root: &Path) -> bool {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    workspaces.iter().any(|ws| {
+        ws.name == name
+            && ws
+                .path
+                .as_deref()
+                .is_none_or(|path| canonical(path) == canonical(workspace_root))
+    })
+}
+
+pub async fn handle_workspace_attach(
+    path: Option<&str>,
+    name: Option<&str>,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    // 1. Locate the existing jj workspace
+    let workspace_root = find_workspace_root(Path::new(path.unwrap_or(".")))?;
+    let repo_root = find_repo_root(&workspace_root)?;
+    let state_manager = StateManager::new(&repo_root);
+
+    let workspace_name = match name {
+        Some(name) => {
+            let vcs = detect_vcs(&workspace_root)?;
+            if !is_vcs_workspace(&vcs.list_workspaces()?, name, &workspace_root) {
+                return Err(crate::error::AetherError::Config(format!(
+                    "'{}' is not the {} workspace at {}; --name must be its {} name",
+                    name,
+                    vcs.kind().as_str(),
+                    workspace_root.display(),
+                    vcs.kind().as_str()
+                )));
+            }
+            name.to_string()
+        }
+        None => default_workspace_name(&repo_root, &workspace_root)?,
+    };
+    if state_manager.get_workspace(&workspace_name)?.is_some() {
+        return Err(crate::error::AetherError::State(format!(
+            "Workspace '{}' already has infrastructure. Detach it first",
+            workspace_name
+        )));
+    }
+
+    // 2. Load the config checked out in that workspace
+    let config = load_workspace_config(config_path, profile, &workspace_root)?;
+
+    // 3. Provision and register infrastructure
+    let state = provision_workspace(
+        &config,
+        &state_manager,
+        &repo_root,
+        &workspace_name,
+        &workspace_root,
+    )
+    .await?;
+
+    // 4. Output
+    if json {
+        let output = AjjOutput {
+            status: "ready".to_string(),
+            operation: "workspace_attach".to_string(),
+            workspace: Some(workspace_info(&state)),
+            errors: vec![],
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!(
+            "✓ Attached {} containers to workspace '{}'",
+            state.resources.len(),
+            state.name
         );
     }
 
     Ok(())
 }
 
-pub async fn handle_workspace_forget(workspace: &str, json: bool) -> Result<()> {
+pub async fn handle_workspace_detach(
+    workspace: Option<&str>,
+    workspace_override: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    // 1. Find the workspace's infrastructure
+    let repo_root = find_repo_root(Path::new("."))?;
+    let state_manager = StateManager::new(&repo_root);
+
+    let state = match workspace {
+        Some(arg) => {
+            let name = resolve_workspace_arg(&state_manager, arg)?;
+            state_manager.get_workspace(&name)?
+        }
+        None => resolve_workspace(&state_manager, workspace_override)?,
+    }
+    .ok_or_else(|| {
+        crate::error::AetherError::State("No Aether infrastructure to detach".to_string())
+    })?;
+
+    // 2. Tear it down, leaving the jj workspace alone
+    let backend = DockerBackend::new()?;
+    backend.deprovision(&state.namespace).await?;
+    state_manager.unregister_workspace(&state.name)?;
+
+    // 3. Output
+    if json {
+        let output = AjjOutput {
+            status: "detached".to_string(),
+            operation: "workspace_detach".to_string(),
+            workspace: Some(workspace_info(&state)),
+            errors: vec![],
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!("✓ Cleaned up {} containers", state.resources.len());
+        println!("✓ Workspace '{}' detached (jj workspace kept)", state.name);
+    }
+
+    Ok(())
+}
+
+/// Resolve a `workspace forget` argument to a workspace name. Arguments
+/// containing `/` are paths: they resolve to the registered workspace rooted
+/// there, or to the directory name, which is what jj names workspaces after.
+fn resolve_workspace_arg(state_manager: &StateManager, arg: &str) -> Result<String> {
+    if !arg.contains('/') {
+        return Ok(arg.to_string());
+    }
+
+    let path = Path::new(arg);
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let registered = state_manager
+        .list_workspaces()?
+        .into_iter()
+        .find(|ws| Path::new(&ws.path) == canonical);
+
+    match registered {
+        Some(ws) => Ok(ws.name),
+        None => canonical
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                crate::error::AetherError::Config(format!("Invalid workspace path: {}", arg))
+            }),
+    }
+}
+
+/// Forget a registered workspace in the VCS, then tear down its
+/// infrastructure and unregister it, journaling enough to bring it back on
+/// `ajj undo`. Named volumes are removed unless the forget can be undone. Unless `force` is given, git keeps a worktree with uncommitted
+/// or untracked work; the workspace then stays registered and provisioned.
+pub(super) async fn forget_registered(
+    vcs: &dyn Vcs,
+    state_manager: &StateManager,
+    state: &WorkspaceState,
+    force: bool,
+) -> Result<()> {
+    let workspace_root = Path::new(&state.path);
+    // Read before a git worktree is removed along with its files
+    let config = load_workspace_config(None, None, workspace_root).ok();
+
+    // The injected file is ajj's own; git would count it as untracked work
+    let injected = match (&config, vcs.kind()) {
+        (Some(config), VcsKind::Git) => config
+            .injection
+            .as_ref()
+            .map(|injection| workspace_root.join(&injection.file))
+            .filter(|path| path.is_file()),
+        _ => None,
+    };
+    let injected = match injected {
+        Some(path) => {
+            let content = std::fs::read(&path)?;
+            std::fs::remove_file(&path)?;
+            Some((path, content))
+        }
+        None => None,
+    };
+    if let Err(e) = vcs.forget_workspace(&state.name, workspace_root, force) {
+        if let Some((path, content)) = injected {
+            std::fs::write(path, content)?;
+        }
+        return Err(refused_hint(e, force));
+    }
+
+    let backend = DockerBackend::new()?;
+    backend.deprovision(&state.namespace).await?;
+    state_manager.unregister_workspace(&state.name)?;
+
+    // While `ajj undo` can bring the workspace back, its volumes stay for it
+    // to re-provision onto; `ajj cleanup` removes them once it no longer can
+    let operation = vcs.current_operation();
+    let undoable = journal(
+        state_manager,
+        operation,
+        OperationKind::WorkspaceForget,
+        state,
+        config,
+    );
+    if !undoable {
+        backend.remove_volumes(&state.namespace).await?;
+    }
+    Ok(())
+}
+
+/// Point out `--force` when git refused to remove a worktree with changes.
+fn refused_hint(error: crate::error::AetherError, force: bool) -> crate::error::AetherError {
+    match error {
+        crate::error::AetherError::Git { message, exit_code } if !force => {
+            crate::error::AetherError::Git {
+                message: format!(
+                    "{}\nThe workspace is kept. Commit or remove its changes, or run \
+                     `ajj workspace forget --force` to discard them",
+                    message.trim_end()
+                ),
+                exit_code,
+            }
+        }
+        error => error,
+    }
+}
+
+pub async fn handle_workspace_forget(workspace: &str, force: bool, json: bool) -> Result<()> {
     // 1. Find repo root and load state
     let repo_root = find_repo_root(Path::new("."))?;
     let state_manager = StateManager::new(&repo_root);
 
     // 2. Get workspace state
+    let vcs = detect_vcs(Path::new("."))?;
+    let arg = workspace;
+    let name = resolve_workspace_arg(&state_manager, arg)?;
+    let workspace = name.as_str();
     let workspace_state = state_manager.get_workspace(workspace)?;
 
-    // 3. Deprovision if found
-    let removed_count = if let Some(state) = workspace_state {
-        let backend = DockerBackend::new()?;
-        backend.deprovision(&state.namespace).await?;
-        state_manager.unregister_workspace(workspace)?;
+    // 3. Deprovision if found, then forget in the VCS
+    let removed_count = if let Some(state) = &workspace_state {
+        forget_registered(vcs.as_ref(), &state_manager, state, force).await?;
         state.resources.len()
     } else {
         if !json {
-            println!("⚠ Workspace not found in state (continuing with jj operation)");
+            println!(
+                "⚠ Workspace not found in state (continuing with {} operation)",
+                vcs.kind().as_str()
+            );
         }
+        let path = vcs
+            .list_workspaces()?
+            .into_iter()
+            .find(|ws| ws.name == workspace)
+            .ok_or_else(|| {
+                crate::error::AetherError::Config(format!(
+                    "No {} workspace named '{}'",
+                    vcs.kind().as_str(),
+                    workspace
+                ))
+            })?
+            .path
+            .unwrap_or_else(|| PathBuf::from(arg));
+        vcs.forget_workspace(workspace, &path, force)
+            .map_err(|e| refused_hint(e, force))?;
         0
     };
 
-    // 4. Execute jj workspace forget
-    let jj_cmd = JjCommand::workspace_forget(workspace);
-    jj_cmd.execute()?;
-
     if json {
         let output = AjjOutput {
             status: "removed".to_string(),
@@ -247,3 +766,150 @@ pub async fn handle_workspace_forget(workspace: &str, json: bool) -> Result<()>
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_default_workspace_name() {
+        let repo_root = Path::new("/work/repo");
+        assert_eq!(
+            default_workspace_name(repo_root, repo_root).unwrap(),
+            "default"
+        );
+        assert_eq!(
+            default_workspace_name(repo_root, Path::new("/work/feature-x")).unwrap(),
+            "feature-x"
+        );
+    }
+
+    #[test]
+    fn test_load_workspace_config_from_checkout() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("aether.toml"),
+            "[backend]\ntype = \"docker\"\n\n[services.redis]\nimage = \"redis:7\"\n",
+        )
+        .unwrap();
+        let checkout = temp_dir.path().join("old-release");
+        std::fs::create_dir_all(checkout.join(".jj")).unwrap();
+        std::fs::write(
+            checkout.join("aether.toml"),
+            "[backend]\ntype = \"docker\"\n\n[services.postgres]\nimage = \"postgres:13\"\n",
+        )
+        .unwrap();
+
+        let config = load_workspace_config(None, None, &checkout).unwrap();
+        assert!(config.services.contains_key("postgres"));
+        assert!(!config.services.contains_key("redis"));
+
+        // A checkout without aether.toml must not fall back to an outer one
+        let bare = temp_dir.path().join("bare");
+        std::fs::create_dir_all(bare.join(".jj")).unwrap();
+        assert!(load_workspace_config(None, None, &bare).is_err());
+    }
+
+    #[test]
+    fn test_is_vcs_workspace() {
+        let temp_dir = TempDir::new().unwrap();
+        let worktrees = vec![VcsWorkspace {
+            name: "feature-x".to_string(),
+            path: Some(temp_dir.path().to_path_buf()),
+        }];
+        assert!(is_vcs_workspace(&worktrees, "feature-x", temp_dir.path()));
+        assert!(!is_vcs_workspace(&worktrees, "custom", temp_dir.path()));
+        assert!(!is_vcs_workspace(
+            &worktrees,
+            "feature-x",
+            &temp_dir.path().join("other")
+        ));
+
+        // jj does not report workspace roots
+        let workspaces = vec![VcsWorkspace {
+            name: "agent-1".to_string(),
+            path: None,
+        }];
+        assert!(is_vcs_workspace(&workspaces, "agent-1", temp_dir.path()));
+        assert!(!is_vcs_workspace(&workspaces, "custom", temp_dir.path()));
+    }
+
+    #[test]
+    fn test_resolve_bind() {
+        let root = Path::new("/nonexistent/ws");
+        assert_eq!(
+            resolve_bind(root, "./init:/docker-entrypoint-initdb.d:ro"),
+            "/nonexistent/ws/init:/docker-entrypoint-initdb.d:ro"
+        );
+        assert_eq!(resolve_bind(root, ".:/app"), "/nonexistent/ws:/app");
+        assert_eq!(
+            resolve_bind(root, "../shared:/shared"),
+            "/nonexistent/ws/../shared:/shared"
+        );
+        assert_eq!(
+            resolve_bind(root, "pgdata:/var/lib/postgresql/data"),
+            "pgdata:/var/lib/postgresql/data"
+        );
+        assert_eq!(resolve_bind(root, "/abs:/abs"), "/abs:/abs");
+    }
+
+    #[test]
+    fn test_env_file_falls_back_to_main_checkout() {
+        let temp_dir = TempDir::new().unwrap();
+        let main = temp_dir.path().join("main");
+        std::fs::create_dir_all(main.join(".jj/repo")).unwrap();
+        std::fs::write(main.join(".env"), "SECRET=main\n").unwrap();
+        let workspace = temp_dir.path().join("feature");
+        std::fs::create_dir_all(workspace.join(".jj")).unwrap();
+        std::fs::write(workspace.join(".jj/repo"), "../../main/.jj/repo").unwrap();
+
+        let found = env_file_path(&workspace, ".env").unwrap();
+        assert_eq!(found, main.canonicalize().unwrap().join(".env"));
+
+        std::fs::write(workspace.join(".env"), "SECRET=feature\n").unwrap();
+        assert_eq!(
+            env_file_path(&workspace, ".env"),
+            Some(workspace.join(".env"))
+        );
+        assert_eq!(env_file_path(&workspace, "missing.env"), None);
+    }
+
+    #[test]
+    fn test_resolve_workspace_arg() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_manager = StateManager::new(temp_dir.path());
+        let ws_path = temp_dir.path().join("checkout");
+        std::fs::create_dir_all(&ws_path).unwrap();
+
+        state_manager
+            .register_workspace(WorkspaceState {
+                name: "feature-x".to_string(),
+                path: ws_path
+                    .canonicalize()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+                namespace: "aether-test-feature-x".to_string(),
+                backend_type: "docker".to_string(),
+                created_at: "2026-01-28T00:00:00Z".to_string(),
+                change_id: None,
+                resources: vec![],
+            })
+            .unwrap();
+
+        let by_path = format!("{}/", ws_path.display());
+        assert_eq!(
+            resolve_workspace_arg(&state_manager, &by_path).unwrap(),
+            "feature-x"
+        );
+        assert_eq!(
+            resolve_workspace_arg(&state_manager, "../elsewhere/other").unwrap(),
+            "other"
+        );
+        assert_eq!(
+            resolve_workspace_arg(&state_manager, "feature-x").unwrap(),
+            "feature-x"
+        );
+    }
+}
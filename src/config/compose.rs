@@ -0,0 +1,655 @@
+//! Conversion of docker-compose files into `aether.toml` services.
+
+use super::schema::{
+    parse_duration, parse_memory_to_bytes, parse_restart, HealthcheckConfig, ResourceLimits,
+    ServiceConfig, Ulimit,
+};
+use crate::error::{AetherError, Result};
+use serde_yaml::{Mapping, Value};
+
+/// Compose service keys that have an Aether equivalent.
+const SUPPORTED_KEYS: &[&str] = &[
+    "image",
+    "ports",
+    "expose",
+    "environment",
+    "volumes",
+    "command",
+    "depends_on",
+    "healthcheck",
+    "deploy",
+    "entrypoint",
+    "working_dir",
+    "user",
+    "hostname",
+    "env_file",
+    "extra_hosts",
+    "tmpfs",
+    "shm_size",
+    "ulimits",
+    "cap_add",
+    "cap_drop",
+    "labels",
+    "restart",
+    "stop_grace_period",
+];
+
+/// Services converted from a compose file, in file order, with everything
+/// that could not be carried over.
+#[derive(Debug, Default)]
+pub struct ComposeImport {
+    pub services: Vec<(String, ServiceConfig)>,
+    pub warnings: Vec<String>,
+}
+
+/// Convert the compose file `source` into service configs.
+pub fn import_compose(source: &str) -> Result<ComposeImport> {
+    let document: Value = serde_yaml::from_str(source)
+        .map_err(|e| AetherError::Config(format!("Failed to parse compose file: {}", e)))?;
+    let services = document
+        .get("services")
+        .and_then(Value::as_mapping)
+        .ok_or_else(|| AetherError::Config("Compose file has no `services`".to_string()))?;
+
+    let mut import = ComposeImport::default();
+    for key in ["networks", "secrets", "configs"] {
+        if document.get(key).is_some() {
+            import
+                .warnings
+                .push(format!("top-level `{}` is not supported, skipped", key));
+        }
+    }
+
+    for (name, service) in services {
+        let Some(name) = name.as_str() else {
+            continue;
+        };
+        let Some(service) = service.as_mapping() else {
+            continue;
+        };
+        let mut converter = Converter {
+            service: name,
+            warnings: &mut import.warnings,
+        };
+        if let Some(config) = converter.service(service) {
+            import.services.push((name.to_string(), config));
+        }
+    }
+
+    // Dependencies on services that were skipped would not validate
+    let names: Vec<String> = import.services.iter().map(|(n, _)| n.clone()).collect();
+    for (name, service) in import.services.iter_mut() {
+        service.depends_on.retain(|dependency| {
+            let known = names.contains(dependency);
+            if !known {
+                import.warnings.push(format!(
+                    "service `{}`: dropped dependency on skipped service `{}`",
+                    name, dependency
+                ));
+            }
+            known
+        });
+    }
+
+    Ok(import)
+}
+
+struct Converter<'a> {
+    service: &'a str,
+    warnings: &'a mut Vec<String>,
+}
+
+impl Converter<'_> {
+    fn warn(&mut self, message: impl std::fmt::Display) {
+        self.warnings
+            .push(format!("service `{}`: {}", self.service, message));
+    }
+
+    fn service(&mut self, service: &Mapping) -> Option<ServiceConfig> {
+        for key in service.keys().filter_map(Value::as_str) {
+            if !SUPPORTED_KEYS.contains(&key) {
+                self.warn(format_args!("`{}` is not supported, skipped", key));
+            }
+        }
+
+        let Some(image) = service.get("image").and_then(scalar) else {
+            self.warn("has no `image` (building images is not supported), skipped");
+            return None;
+        };
+
+        let mut config = ServiceConfig {
+            image: unescape(&image),
+            ..Default::default()
+        };
+        if let Some(ports) = service.get("ports").and_then(Value::as_sequence) {
+            for port in ports {
+                self.port(port, &mut config.ports);
+            }
+        }
+        // Ports exposed to other services only; Aether publishes every port
+        if let Some(expose) = service.get("expose").and_then(Value::as_sequence) {
+            for port in expose {
+                self.port(port, &mut config.ports);
+            }
+        }
+        if let Some(environment) = service.get("environment") {
+            self.environment(environment, &mut config);
+        }
+        if let Some(volumes) = service.get("volumes").and_then(Value::as_sequence) {
+            for volume in volumes {
+                if let Some(volume) = self.volume(volume) {
+                    config.volumes.push(volume);
+                }
+            }
+        }
+        config.command = service.get("command").and_then(|c| self.command(c));
+        config.depends_on = match service.get("depends_on") {
+            Some(Value::Sequence(names)) => names.iter().filter_map(scalar).collect(),
+            Some(Value::Mapping(names)) => names
+                .iter()
+                .filter_map(|(name, options)| {
+                    let name = scalar(name)?;
+                    let condition = options.get("condition").and_then(scalar);
+                    if let Some(condition) = condition.filter(|c| c != "service_started") {
+                        self.warn(format_args!(
+                            "`depends_on.{}.condition: {}` is not supported, \
+                             `{}` is only started first",
+                            name, condition, name
+                        ));
+                    }
+                    Some(name)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        if let Some(healthcheck) = service.get("healthcheck").and_then(Value::as_mapping) {
+            config.healthcheck = self.healthcheck(healthcheck);
+        }
+        if let Some(deploy) = service.get("deploy").and_then(Value::as_mapping) {
+            config.resources = self.resources(deploy);
+        }
+        self.container_options(service, &mut config);
+
+        Some(config)
+    }
+
+    fn container_options(&mut self, service: &Mapping, config: &mut ServiceConfig) {
+        let text = |key: &str| service.get(key).and_then(scalar).map(|v| unescape(&v));
+        config.entrypoint = service.get("entrypoint").and_then(|e| self.command(e));
+        config.working_dir = text("working_dir");
+        config.user = text("user");
+        config.hostname = text("hostname");
+        config.env_file = match service.get("env_file") {
+            Some(Value::Sequence(files)) => files
+                .iter()
+                .filter_map(|file| scalar(file).or_else(|| file.get("path").and_then(scalar)))
+                .collect(),
+            Some(file) => scalar(file).into_iter().collect(),
+            None => Vec::new(),
+        };
+        config.extra_hosts = entries(service.get("extra_hosts"), ':');
+        config.tmpfs = match service.get("tmpfs") {
+            Some(Value::Sequence(mounts)) => mounts.iter().filter_map(scalar).collect(),
+            Some(mount) => scalar(mount).into_iter().collect(),
+            None => Vec::new(),
+        };
+        config.shm_size = text("shm_size").and_then(|size| self.memory(&size, "shm_size"));
+        config.cap_add = strings(service.get("cap_add"));
+        config.cap_drop = strings(service.get("cap_drop"));
+        config.labels = entries(service.get("labels"), '=')
+            .into_iter()
+            .map(|label| match label.split_once('=') {
+                Some((key, value)) => (key.to_string(), unescape(value)),
+                None => (label, String::new()),
+            })
+            .collect();
+
+        if let Some(ulimits) = service.get("ulimits").and_then(Value::as_mapping) {
+            for (name, ulimit) in ulimits {
+                let Some(name) = scalar(name) else {
+                    continue;
+                };
+                let limit = |value: Option<&Value>| value.and_then(Value::as_i64);
+                let ulimit = match ulimit {
+                    Value::Mapping(pair) => limit(pair.get("soft"))
+                        .zip(limit(pair.get("hard")))
+                        .map(|(soft, hard)| Ulimit::Pair { soft, hard }),
+                    single => limit(Some(single)).map(Ulimit::Single),
+                };
+                match ulimit {
+                    Some(ulimit) => {
+                        config.ulimits.insert(name, ulimit);
+                    }
+                    None => self.warn(format_args!("invalid ulimit `{}`, skipped", name)),
+                }
+            }
+        }
+
+        if let Some(restart) = text("restart") {
+            if parse_restart(&restart).is_ok() {
+                config.restart = Some(restart);
+            } else {
+                self.warn(format_args!(
+                    "restart policy `{}` is not supported, skipped",
+                    restart
+                ));
+            }
+        }
+        if let Some(period) = text("stop_grace_period") {
+            if parse_duration(&period).is_ok() {
+                config.stop_grace_period = Some(period);
+            } else {
+                self.warn(format_args!(
+                    "stop_grace_period `{}` is not supported, skipped",
+                    period
+                ));
+            }
+        }
+    }
+
+    /// A compose memory quantity in the units Aether accepts: `512M` and
+    /// byte counts that are whole kilobytes.
+    fn memory(&mut self, memory: &str, what: &str) -> Option<String> {
+        let memory = match memory.parse::<u64>() {
+            Ok(bytes) if bytes > 0 && bytes % 1024 == 0 => format!("{}k", bytes / 1024),
+            _ => memory.to_lowercase(),
+        };
+        if parse_memory_to_bytes(&memory).is_ok() {
+            Some(memory)
+        } else {
+            self.warn(format_args!(
+                "memory `{}` in {} is not supported, skipped",
+                memory, what
+            ));
+            None
+        }
+    }
+
+    /// Container ports of a short (`"8080:80"`, `"3000-3001"`) or long
+    /// (`{ target: 80 }`) port entry; host ports are allocated by Aether.
+    fn port(&mut self, port: &Value, ports: &mut Vec<String>) {
+        let (container, protocol) = match port {
+            Value::Mapping(long) => (
+                long.get("target").and_then(scalar).unwrap_or_default(),
+                long.get("protocol").and_then(scalar),
+            ),
+            _ => {
+                let short = scalar(port).unwrap_or_default();
+                let (short, protocol) = match short.split_once('/') {
+                    Some((short, protocol)) => (short.to_string(), Some(protocol.to_string())),
+                    None => (short, None),
+                };
+                let container = short.rsplit(':').next().unwrap_or_default().to_string();
+                (container, protocol)
+            }
+        };
+        if protocol.is_some_and(|p| p != "tcp") {
+            self.warn(format_args!(
+                "port `{}` is not TCP, skipped",
+                scalar(port).unwrap_or(container)
+            ));
+            return;
+        }
+
+        let range = match container.split_once('-') {
+            Some((start, end)) => start.parse::<u16>().ok().zip(end.parse::<u16>().ok()),
+            None => container.parse::<u16>().ok().map(|p| (p, p)),
+        };
+        match range {
+            Some((start, end)) if start > 0 && start <= end => {
+                for number in start..=end {
+                    let number = number.to_string();
+                    if !ports.contains(&number) {
+                        ports.push(number);
+                    }
+                }
+            }
+            _ => self.warn(format_args!("invalid port `{}`, skipped", container)),
+        }
+    }
+
+    fn environment(&mut self, environment: &Value, config: &mut ServiceConfig) {
+        let entries: Vec<(String, Option<String>)> = match environment {
+            Value::Mapping(map) => map
+                .iter()
+                .filter_map(|(k, v)| Some((scalar(k)?, scalar(v))))
+                .collect(),
+            Value::Sequence(list) => list
+                .iter()
+                .filter_map(scalar)
+                .map(|entry| match entry.split_once('=') {
+                    Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                    None => (entry, None),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        for (key, value) in entries {
+            // A bare key passes the variable through from the environment
+            let value = match value {
+                Some(value) => unescape(&value),
+                None => format!("${{{}:-}}", key),
+            };
+            config.env.insert(key, value);
+        }
+    }
+
+    fn volume(&mut self, volume: &Value) -> Option<String> {
+        let Value::Mapping(long) = volume else {
+            return scalar(volume);
+        };
+        let kind = long.get("type").and_then(scalar).unwrap_or_default();
+        if kind != "volume" && kind != "bind" {
+            self.warn(format_args!(
+                "`{}` volumes are not supported, skipped",
+                kind
+            ));
+            return None;
+        }
+        let target = long.get("target").and_then(scalar)?;
+        let mut spec = match long.get("source").and_then(scalar) {
+            Some(source) => format!("{}:{}", source, target),
+            None => target,
+        };
+        if long.get("read_only").and_then(Value::as_bool) == Some(true) {
+            spec.push_str(":ro");
+        }
+        Some(spec)
+    }
+
+    fn command(&mut self, command: &Value) -> Option<Vec<String>> {
+        match command {
+            Value::Sequence(args) => Some(args.iter().filter_map(scalar).collect()),
+            _ => {
+                let command = scalar(command)?;
+                let args = split_command(&command);
+                if args.is_none() {
+                    self.warn(format_args!(
+                        "could not split command `{}`, skipped",
+                        command
+                    ));
+                }
+                args
+            }
+        }
+    }
+
+    fn healthcheck(&mut self, healthcheck: &Mapping) -> Option<HealthcheckConfig> {
+        if healthcheck.get("disable").and_then(Value::as_bool) == Some(true) {
+            return None;
+        }
+        let test = match healthcheck.get("test")? {
+            Value::Sequence(test) => test.iter().filter_map(scalar).collect(),
+            test => vec!["CMD-SHELL".to_string(), scalar(test)?],
+        };
+        if test.first().is_some_and(|t| t == "NONE") {
+            return None;
+        }
+        let duration = |key: &str| healthcheck.get(key).and_then(scalar);
+        Some(HealthcheckConfig {
+            test,
+            interval: duration("interval"),
+            timeout: duration("timeout"),
+            retries: healthcheck
+                .get("retries")
+                .and_then(Value::as_u64)
+                .and_then(|r| u32::try_from(r).ok()),
+            start_period: duration("start_period"),
+        })
+    }
+
+    fn resources(&mut self, deploy: &Mapping) -> Option<ResourceLimits> {
+        for key in deploy.keys().filter_map(Value::as_str) {
+            if key != "resources" {
+                self.warn(format_args!("`deploy.{}` is not supported, skipped", key));
+            }
+        }
+        let resources = deploy.get("resources")?;
+        let cpus = |section: &str| {
+            resources
+                .get(section)?
+                .get("cpus")
+                .and_then(scalar)?
+                .parse::<f64>()
+                .ok()
+        };
+        let mut memory = |section: &str| {
+            let memory = resources.get(section)?.get("memory").and_then(scalar)?;
+            self.memory(&memory, &format!("deploy.resources.{}", section))
+        };
+
+        let limits = ResourceLimits {
+            memory_limit: memory("limits"),
+            memory_reservation: memory("reservations"),
+            cpu_limit: cpus("limits"),
+            cpu_reservation: cpus("reservations"),
+        };
+        let any = limits.cpu_limit.is_some()
+            || limits.cpu_reservation.is_some()
+            || limits.memory_limit.is_some()
+            || limits.memory_reservation.is_some();
+        any.then_some(limits)
+    }
+}
+
+/// A string, number or boolean as a string.
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_sequence)
+        .map(|items| items.iter().filter_map(scalar).collect())
+        .unwrap_or_default()
+}
+
+/// A list of `key<separator>value` strings, or a mapping written as one.
+fn entries(value: Option<&Value>, separator: char) -> Vec<String> {
+    match value {
+        Some(Value::Mapping(map)) => map
+            .iter()
+            .filter_map(|(k, v)| {
+                let key = scalar(k)?;
+                Some(match scalar(v) {
+                    Some(v) => format!("{}{}{}", key, separator, v),
+                    None => key,
+                })
+            })
+            .collect(),
+        value => strings(value),
+    }
+}
+
+/// Compose's `$$` escape as Aether writes it: `$$` is a plain `$`, except
+/// before `{` where Aether needs `$${`.
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(at) = rest.find("$$") {
+        out.push_str(&rest[..at]);
+        rest = &rest[at + 2..];
+        out.push_str(if rest.starts_with('{') { "$$" } else { "$" });
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Split a shell-form command on whitespace, honouring single and double
+/// quotes. `None` for unbalanced quotes.
+fn split_command(command: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Some(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_compose() {
+        let import = import_compose(
+            r#"
+services:
+  db:
+    image: postgres:16
+    ports: ["5432:5432", "127.0.0.1:9187:9187/tcp"]
+    expose: ["9090", 9091]
+    environment:
+      POSTGRES_PASSWORD: ${DB_PASSWORD:-devpass}
+      POSTGRES_PORT: 5432
+    volumes:
+      - pgdata:/var/lib/postgresql/data
+      - type: bind
+        source: ./init
+        target: /docker-entrypoint-initdb.d
+        read_only: true
+    healthcheck:
+      test: pg_isready -U postgres
+      interval: 5s
+      retries: 5
+    deploy:
+      resources:
+        limits: { cpus: "0.5", memory: 512M }
+  app:
+    build: .
+  worker:
+    image: app:latest
+    command: sh -c "echo 'a b' && sleep 1"
+    environment: ["MODE=worker", "API_KEY"]
+    depends_on: { db: { condition: service_healthy }, app: {} }
+    ports: ["53:53/udp", "3000-3001"]
+    container_name: worker
+    restart: on-failure:3
+    entrypoint: ["/bin/tini", "--"]
+    ulimits: { nofile: { soft: 1024, hard: 65536 }, nproc: 512 }
+    labels: ["team=platform", "price=$$5"]
+    extra_hosts: { host.docker.internal: host-gateway }
+    shm_size: 268435456
+    stop_grace_period: 1m30s
+    cap_drop: [ALL]
+volumes:
+  pgdata:
+networks:
+  default:
+"#,
+        )
+        .unwrap();
+
+        let names: Vec<&str> = import.services.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["db", "worker"]);
+
+        let db = &import.services[0].1;
+        assert_eq!(db.image, "postgres:16");
+        assert_eq!(db.ports, vec!["5432", "9187", "9090", "9091"]);
+        assert_eq!(db.env["POSTGRES_PASSWORD"], "${DB_PASSWORD:-devpass}");
+        assert_eq!(db.env["POSTGRES_PORT"], "5432");
+        assert_eq!(
+            db.volumes,
+            vec![
+                "pgdata:/var/lib/postgresql/data",
+                "./init:/docker-entrypoint-initdb.d:ro"
+            ]
+        );
+        let healthcheck = db.healthcheck.as_ref().unwrap();
+        assert_eq!(
+            healthcheck.test,
+            vec!["CMD-SHELL", "pg_isready -U postgres"]
+        );
+        assert_eq!(healthcheck.retries, Some(5));
+        let resources = db.resources.as_ref().unwrap();
+        assert_eq!(resources.cpu_limit, Some(0.5));
+        assert_eq!(resources.memory_limit.as_deref(), Some("512m"));
+
+        let worker = &import.services[1].1;
+        assert_eq!(
+            worker.command.as_ref().unwrap(),
+            &vec!["sh", "-c", "echo 'a b' && sleep 1"]
+        );
+        assert_eq!(worker.env["MODE"], "worker");
+        assert_eq!(worker.env["API_KEY"], "${API_KEY:-}");
+        assert_eq!(worker.depends_on, vec!["db"]);
+        assert_eq!(worker.ports, vec!["3000", "3001"]);
+        assert_eq!(worker.restart.as_deref(), Some("on-failure:3"));
+        assert_eq!(
+            worker.entrypoint.as_ref().unwrap(),
+            &vec!["/bin/tini", "--"]
+        );
+        assert_eq!(
+            worker.ulimits["nofile"],
+            Ulimit::Pair {
+                soft: 1024,
+                hard: 65536
+            }
+        );
+        assert_eq!(worker.ulimits["nproc"], Ulimit::Single(512));
+        assert_eq!(worker.labels["price"], "$5");
+        assert_eq!(
+            worker.extra_hosts,
+            vec!["host.docker.internal:host-gateway"]
+        );
+        assert_eq!(worker.shm_size.as_deref(), Some("262144k"));
+        assert_eq!(worker.stop_grace_period.as_deref(), Some("1m30s"));
+        assert_eq!(worker.cap_drop, vec!["ALL"]);
+
+        assert_eq!(
+            import.warnings,
+            vec![
+                "top-level `networks` is not supported, skipped",
+                "service `app`: `build` is not supported, skipped",
+                "service `app`: has no `image` (building images is not supported), skipped",
+                "service `worker`: `container_name` is not supported, skipped",
+                "service `worker`: port `53:53/udp` is not TCP, skipped",
+                "service `worker`: `depends_on.db.condition: service_healthy` is not supported, \
+                 `db` is only started first",
+                "service `worker`: dropped dependency on skipped service `app`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unescape_and_split() {
+        assert_eq!(unescape("cost $$5, $${HOME}"), "cost $5, $${HOME}");
+        assert_eq!(
+            split_command("redis-server --save ''").unwrap(),
+            vec!["redis-server", "--save", ""]
+        );
+        assert!(split_command("echo 'open").is_none());
+    }
+}
@@ -0,0 +1,409 @@
+//! `extends = "<name>"` on services: a service starts from a `[templates]`
+//! entry (or, failing that, another service) and overrides it field by
+//! field. Tables such as `env`, `resources` and `labels` merge key by key,
+//! `ports` and `volumes` are combined (a volume replaces the base's mount at
+//! the same container path), and everything else replaces the base's value.
+//!
+//! `preset = "<name>"` works the same way with a built-in preset as the
+//! base. Presets are applied before `extends`.
+
+use super::presets::find_preset;
+use toml_edit::{Array, DocumentMut, Item, Table, TableLike, Value};
+
+/// Document path a node of an expanded service was copied from.
+pub type Alias = (Vec<String>, Vec<String>);
+
+fn path(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|p| p.to_string()).collect()
+}
+
+/// Document path of the base `name` refers to: a template, or else a
+/// service.
+pub fn base_path(
+    templates: Option<&Table>,
+    services: Option<&Table>,
+    name: &str,
+) -> Option<Vec<String>> {
+    if templates.is_some_and(|t| t.get(name).is_some_and(Item::is_table_like)) {
+        return Some(path(&["templates", name]));
+    }
+    if services.is_some_and(|s| s.get(name).is_some_and(Item::is_table_like)) {
+        return Some(path(&["services", name]));
+    }
+    None
+}
+
+fn table_at<'a>(document: &'a DocumentMut, at: &[String]) -> Option<&'a Table> {
+    let mut table = document.as_table();
+    for segment in at {
+        table = table.get(segment)?.as_table()?;
+    }
+    Some(table)
+}
+
+/// Every node path below `item`, relative to it: keys and array elements.
+fn node_paths(item: &Item, rel: &mut Vec<String>, out: &mut Vec<Vec<String>>) {
+    match item {
+        Item::Value(Value::Array(array)) => {
+            for i in 0..array.len() {
+                rel.push(i.to_string());
+                out.push(rel.clone());
+                rel.pop();
+            }
+        }
+        _ => {
+            for (key, child) in item.as_table_like().into_iter().flat_map(|t| t.iter()) {
+                rel.push(key.to_string());
+                out.push(rel.clone());
+                node_paths(child, rel, out);
+                rel.pop();
+            }
+        }
+    }
+}
+
+/// Sources of the nodes below `key` of `own`, defined at `own_path`.
+fn own_sources(own: &dyn TableLike, own_path: &[String], key: &str) -> Vec<Alias> {
+    let mut rel = vec![key.to_string()];
+    let mut paths = vec![rel.clone()];
+    if let Some(item) = own.get(key) {
+        node_paths(item, &mut rel, &mut paths);
+    }
+    paths
+        .into_iter()
+        .map(|rel| {
+            let mut source = own_path.to_vec();
+            source.extend(rel.iter().cloned());
+            (rel, source)
+        })
+        .collect()
+}
+
+fn drop_under(sources: &mut Vec<Alias>, prefix: &[String]) {
+    sources.retain(|(rel, _)| !rel.starts_with(prefix));
+}
+
+/// Container path of a `source:target[:mode]` volume.
+fn volume_target(volume: &str) -> &str {
+    let mut parts = volume.split(':');
+    let first = parts.next().unwrap_or(volume);
+    parts.next().unwrap_or(first)
+}
+
+/// `base` overridden by `own` (defined at `own_path`), with the source of
+/// every node of the result.
+fn merge(
+    base: Table,
+    mut sources: Vec<Alias>,
+    own: &Table,
+    own_path: &[String],
+) -> (Table, Vec<Alias>) {
+    let mut result = base;
+    result.remove("extends");
+    drop_under(&mut sources, &path(&["extends"]));
+
+    for (key, item) in own.iter() {
+        let key_path = vec![key.to_string()];
+        match (key, item, result.get(key)) {
+            (
+                "ports" | "volumes",
+                Item::Value(Value::Array(own_array)),
+                Some(Item::Value(Value::Array(base_array))),
+            ) => {
+                let is_volumes = key == "volumes";
+                let own_values: Vec<&str> = own_array.iter().filter_map(Value::as_str).collect();
+                let mut merged = Array::new();
+                let mut merged_sources = vec![(
+                    key_path.clone(),
+                    own_path.iter().cloned().chain(key_path.clone()).collect(),
+                )];
+
+                for (i, value) in base_array.iter().enumerate() {
+                    let replaced = value.as_str().is_some_and(|v| {
+                        own_values.iter().any(|o| {
+                            if is_volumes {
+                                volume_target(o) == volume_target(v)
+                            } else {
+                                *o == v
+                            }
+                        })
+                    });
+                    if replaced {
+                        continue;
+                    }
+                    let element = path(&[key, &i.to_string()]);
+                    let source = sources
+                        .iter()
+                        .find(|(rel, _)| *rel == element)
+                        .map(|(_, source)| source.clone());
+                    let index = merged.len().to_string();
+                    merged.push_formatted(value.clone());
+                    if let Some(source) = source {
+                        merged_sources.push((path(&[key, &index]), source));
+                    }
+                }
+                for (j, value) in own_array.iter().enumerate() {
+                    let index = merged.len().to_string();
+                    merged.push_formatted(value.clone());
+                    let mut source = own_path.to_vec();
+                    source.extend([key.to_string(), j.to_string()]);
+                    merged_sources.push((path(&[key, &index]), source));
+                }
+
+                drop_under(&mut sources, &key_path);
+                sources.extend(merged_sources);
+                result.insert(key, Item::Value(Value::Array(merged)));
+            }
+            (_, own_item, Some(base_item))
+                if own_item.is_table_like() && base_item.is_table_like() =>
+            {
+                let own_table = own_item.clone().into_table().expect("matched a table");
+                let mut base_table = base_item.clone().into_table().expect("matched a table");
+                let mut table_path = own_path.to_vec();
+                table_path.push(key.to_string());
+                // Keys not inherited must not resolve through the base's table
+                sources.retain(|(rel, _)| *rel != key_path);
+                sources.push((key_path.clone(), table_path.clone()));
+                for (child, value) in own_table.iter() {
+                    base_table.insert(child, value.clone());
+                    let child_path = path(&[key, child]);
+                    drop_under(&mut sources, &child_path);
+                    sources.extend(own_sources(&own_table, &table_path, child).into_iter().map(
+                        |(rel, source)| {
+                            let mut full = key_path.clone();
+                            full.extend(rel);
+                            (full, source)
+                        },
+                    ));
+                }
+                result.insert(key, Item::Table(base_table));
+            }
+            _ => {
+                result.insert(key, item.clone());
+                drop_under(&mut sources, &key_path);
+                sources.extend(own_sources(own, own_path, key));
+            }
+        }
+    }
+
+    (result, sources)
+}
+
+/// The table at `at` with its `extends` chain applied, and the source of
+/// every node. Unknown bases and cycles leave the table as is; validation
+/// reports them.
+fn resolve(
+    document: &DocumentMut,
+    at: &[String],
+    visiting: &mut Vec<Vec<String>>,
+) -> Option<(Table, Vec<Alias>)> {
+    let own = table_at(document, at)?;
+    let identity = || {
+        let mut paths = Vec::new();
+        node_paths(&Item::Table(own.clone()), &mut Vec::new(), &mut paths);
+        paths
+            .into_iter()
+            .map(|rel| {
+                let mut source = at.to_vec();
+                source.extend(rel.iter().cloned());
+                (rel, source)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let Some(name) = own.get("extends").and_then(Item::as_str) else {
+        return Some((own.clone(), identity()));
+    };
+    let base = base_path(
+        document.get("templates").and_then(Item::as_table),
+        document.get("services").and_then(Item::as_table),
+        name,
+    );
+    let Some(base) = base.filter(|b| b != at && !visiting.contains(b)) else {
+        return Some((own.clone(), identity()));
+    };
+
+    visiting.push(at.to_vec());
+    let resolved = resolve(document, &base, visiting);
+    visiting.pop();
+    let (base_table, base_sources) = resolved?;
+    Some(merge(base_table, base_sources, own, at))
+}
+
+/// Apply `preset` to every template and service of `document`. Returns,
+/// for nodes the preset supplied, the path of the `preset` key.
+pub fn expand_presets(document: &mut DocumentMut) -> Vec<Alias> {
+    let mut aliases = Vec::new();
+    for section in ["templates", "services"] {
+        let Some(tables) = document.get_mut(section).and_then(Item::as_table_mut) else {
+            continue;
+        };
+        for (name, item) in tables.iter_mut() {
+            let Some(own) = item.as_table() else {
+                continue;
+            };
+            let Some(preset) = own
+                .get("preset")
+                .and_then(Item::as_str)
+                .and_then(find_preset)
+            else {
+                continue;
+            };
+
+            let at = path(&[section, &name]);
+            let mut preset_key = at.clone();
+            preset_key.push("preset".to_string());
+            let base = preset.table();
+            let mut paths = Vec::new();
+            node_paths(&Item::Table(base.clone()), &mut Vec::new(), &mut paths);
+            let sources = paths
+                .into_iter()
+                .map(|rel| (rel, preset_key.clone()))
+                .collect();
+
+            let (table, sources) = merge(base, sources, own, &at);
+            for (rel, source) in sources {
+                let mut node = at.clone();
+                node.extend(rel);
+                if node != source {
+                    aliases.push((node, source));
+                }
+            }
+            *item = Item::Table(table);
+        }
+    }
+    aliases
+}
+
+/// Apply `extends` to every service of `document`. Returns, for nodes of
+/// expanded services, the document path each was copied from.
+pub fn expand_extends(document: &mut DocumentMut) -> Vec<Alias> {
+    let snapshot = document.clone();
+    let names: Vec<String> = match snapshot.get("services").and_then(Item::as_table) {
+        Some(services) => services
+            .iter()
+            .filter(|(_, s)| s.as_table().is_some_and(|t| t.contains_key("extends")))
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        None => return Vec::new(),
+    };
+
+    let mut aliases = Vec::new();
+    for name in names {
+        let at = path(&["services", &name]);
+        let Some((table, sources)) = resolve(&snapshot, &at, &mut Vec::new()) else {
+            continue;
+        };
+        for (rel, source) in sources {
+            let mut node = at.clone();
+            node.extend(rel);
+            if node != source {
+                aliases.push((node, source));
+            }
+        }
+        if let Some(services) = document.get_mut("services").and_then(Item::as_table_mut) {
+            services.insert(&name, Item::Table(table));
+        }
+    }
+    aliases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AetherConfig;
+
+    #[test]
+    fn test_expand_extends() {
+        let mut document: DocumentMut = r#"
+[backend]
+type = "docker"
+
+[templates.base-postgres]
+image = "postgres:15"
+ports = ["5432"]
+env = { POSTGRES_USER = "app", POSTGRES_PASSWORD = "devpass" }
+volumes = ["./init:/docker-entrypoint-initdb.d", "pgdata:/var/lib/postgresql/data"]
+resources = { memory_limit = "1g" }
+
+[services.postgres-app]
+extends = "base-postgres"
+env = { POSTGRES_DB = "app" }
+
+[services.postgres-analytics]
+extends = "postgres-app"
+image = "postgres:16"
+ports = ["5432", "9187"]
+env = { POSTGRES_DB = "analytics" }
+volumes = ["analytics:/var/lib/postgresql/data"]
+"#
+        .parse()
+        .unwrap();
+
+        let aliases = expand_extends(&mut document);
+        let config: AetherConfig = toml::from_str(&document.to_string()).unwrap();
+
+        let app = &config.services["postgres-app"];
+        assert_eq!(app.image, "postgres:15");
+        assert_eq!(app.ports, vec!["5432"]);
+        assert_eq!(app.env.len(), 3);
+        assert_eq!(app.env["POSTGRES_DB"], "app");
+        assert_eq!(app.extends.as_deref(), Some("base-postgres"));
+
+        let analytics = &config.services["postgres-analytics"];
+        assert_eq!(analytics.image, "postgres:16");
+        assert_eq!(analytics.ports, vec!["5432", "9187"]);
+        assert_eq!(analytics.env["POSTGRES_DB"], "analytics");
+        assert_eq!(analytics.env["POSTGRES_USER"], "app");
+        assert_eq!(
+            analytics.volumes,
+            vec![
+                "./init:/docker-entrypoint-initdb.d",
+                "analytics:/var/lib/postgresql/data"
+            ]
+        );
+        assert_eq!(
+            analytics
+                .resources
+                .as_ref()
+                .unwrap()
+                .memory_limit
+                .as_deref(),
+            Some("1g")
+        );
+
+        let source_of = |node: &[&str]| {
+            aliases
+                .iter()
+                .find(|(n, _)| *n == path(node))
+                .map(|(_, source)| source.join("."))
+        };
+        assert_eq!(
+            source_of(&["services", "postgres-analytics", "env", "POSTGRES_USER"]),
+            Some("templates.base-postgres.env.POSTGRES_USER".to_string())
+        );
+        assert_eq!(
+            source_of(&["services", "postgres-app", "ports", "0"]),
+            Some("templates.base-postgres.ports.0".to_string())
+        );
+        assert_eq!(
+            source_of(&["services", "postgres-analytics", "volumes", "1"]),
+            Some("services.postgres-analytics.volumes.0".to_string())
+        );
+        assert_eq!(
+            source_of(&["services", "postgres-analytics", "image"]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extends_cycle_is_left_alone() {
+        let mut document: DocumentMut =
+            "[services.a]\nextends = \"b\"\n\n[services.b]\nextends = \"a\"\nimage = \"b\"\n"
+                .parse()
+                .unwrap();
+        expand_extends(&mut document);
+        assert!(document["services"]["a"].get("image").is_some());
+        assert!(document["services"]["b"].get("image").is_some());
+    }
+}
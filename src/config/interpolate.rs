@@ -0,0 +1,131 @@
+//! `${VAR}` and `${VAR:-default}` expansion of config values.
+
+use std::fmt;
+
+/// A `${...}` reference that could not be expanded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterpolationError {
+    /// Byte offset of the `${` in the value
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` in `value`, looking variables up
+/// with `lookup`. `${VAR}` requires the variable to be set; the default is
+/// used when it is unset or empty. `$${` is a literal `${`, and any other
+/// `$` is left alone.
+pub fn interpolate(
+    value: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, InterpolationError> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(at) = rest.find('$') {
+        let offset = value.len() - rest.len() + at;
+        expanded.push_str(&rest[..at]);
+        let after = &rest[at..];
+
+        if let Some(literal) = after.strip_prefix("$${") {
+            expanded.push_str("${");
+            rest = literal;
+            continue;
+        }
+        let Some(reference) = after.strip_prefix("${") else {
+            expanded.push('$');
+            rest = &after[1..];
+            continue;
+        };
+        let Some(end) = reference.find('}') else {
+            return Err(InterpolationError {
+                offset,
+                message: "unterminated `${` in value".to_string(),
+            });
+        };
+
+        let (name, default) = match reference[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&reference[..end], None),
+        };
+        if !is_variable_name(name) {
+            return Err(InterpolationError {
+                offset,
+                message: format!("invalid variable name `{}` in `${{...}}`", name),
+            });
+        }
+
+        match (lookup(name), default) {
+            (Some(set), Some(default)) if set.is_empty() => expanded.push_str(default),
+            (Some(set), _) => expanded.push_str(&set),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) => {
+                return Err(InterpolationError {
+                    offset,
+                    message: format!(
+                        "environment variable `{}` is not set (use `${{{}:-default}}` to make it optional)",
+                        name, name
+                    ),
+                })
+            }
+        }
+        rest = &reference[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Look variables up in the process environment.
+pub fn env_lookup(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "USER" => Some("alice".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        assert_eq!(interpolate("db_${USER}", &lookup).unwrap(), "db_alice");
+        assert_eq!(
+            interpolate("${PASSWORD:-devpass}", &lookup).unwrap(),
+            "devpass"
+        );
+        assert_eq!(interpolate("${EMPTY:-x}", &lookup).unwrap(), "x");
+        assert_eq!(interpolate("${EMPTY}", &lookup).unwrap(), "");
+        assert_eq!(
+            interpolate("cost $5, $${USER}", &lookup).unwrap(),
+            "cost $5, ${USER}"
+        );
+    }
+
+    #[test]
+    fn test_interpolate_errors() {
+        let err = interpolate("postgres://${DB_USER}@db", &lookup).unwrap_err();
+        assert_eq!(err.offset, 11);
+        assert!(err.message.contains("`DB_USER` is not set"));
+
+        assert!(interpolate("${USER", &lookup).is_err());
+        assert!(interpolate("${1X}", &lookup).is_err());
+    }
+}
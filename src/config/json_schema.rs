@@ -0,0 +1,78 @@
+//! JSON Schema for `aether.toml`, for editor completion and validation.
+//! Generated from the config types, plus the keys the layering handles
+//! itself (`include`, `templates`, `profiles`).
+
+use super::presets::PRESETS;
+use super::schema::AetherConfig;
+use serde_json::{json, Value};
+
+/// The schema of one `aether.toml` file, as opposed to the merged config:
+/// keys another layer, an `extends` base or a preset can supply are not
+/// required.
+pub fn config_schema() -> Value {
+    let mut schema = schemars::schema_for!(AetherConfig).to_value();
+    schema["title"] = json!("aether.toml");
+
+    // The backend may come from the user-global config
+    remove_required(&mut schema, "backend");
+    let defs = &mut schema["$defs"];
+    remove_required(&mut defs["ServiceConfig"], "image");
+    remove_required(&mut defs["HealthcheckConfig"], "test");
+    let presets: Vec<&str> = PRESETS.iter().map(|p| p.name).collect();
+    defs["ServiceConfig"]["properties"]["preset"]["enum"] = json!(presets);
+
+    let properties = &mut schema["properties"];
+    properties["templates"] = json!({
+        "description": "Service templates for `extends`",
+        "type": "object",
+        "additionalProperties": { "$ref": "#/$defs/ServiceConfig" },
+    });
+    let profile = json!({
+        "type": "object",
+        "properties": {
+            "backend": properties["backend"].clone(),
+            "services": properties["services"].clone(),
+            "templates": properties["templates"].clone(),
+            "injection": properties["injection"].clone(),
+        },
+        "additionalProperties": false,
+    });
+    properties["include"] = json!({
+        "description": "Glob patterns of config files to merge in, relative to this file",
+        "type": "array",
+        "items": { "type": "string" },
+    });
+    properties["profiles"] = json!({
+        "description": "Overrides applied with `--profile <name>`",
+        "type": "object",
+        "additionalProperties": profile,
+    });
+
+    schema
+}
+
+fn remove_required(schema: &mut Value, key: &str) {
+    if let Some(required) = schema.get_mut("required").and_then(Value::as_array_mut) {
+        required.retain(|k| k != key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_schema() {
+        let schema = config_schema();
+        let service = &schema["$defs"]["ServiceConfig"];
+        assert_eq!(service["additionalProperties"], json!(false));
+        assert!(service["required"]
+            .as_array()
+            .is_none_or(|r| !r.contains(&json!("image"))));
+        assert_eq!(service["properties"]["preset"]["enum"][0], "postgres");
+        assert_eq!(
+            schema["properties"]["profiles"]["additionalProperties"]["properties"]["services"],
+            schema["properties"]["services"]
+        );
+    }
+}
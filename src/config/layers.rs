@@ -0,0 +1,587 @@
+//! Layered configuration. Layers are merged in this order, later ones
+//! overriding earlier ones:
+//!
+//! 1. the user-global `~/.config/aether/config.toml`
+//! 2. files matched by `include = ["infra/*.toml"]` in `aether.toml`
+//! 3. `aether.toml`
+//! 4. `aether.local.toml`, the per-developer file kept out of version control
+//! 5. the `[profiles.<name>]` section selected with `--profile`, from each of
+//!    the above
+//!
+//! Tables merge key by key; arrays and other values are replaced whole.
+//! Services are then expanded with the presets and templates they use.
+
+use super::extends::{expand_extends, expand_presets, Alias};
+use super::schema::AetherConfig;
+use super::validate::Diagnostic;
+use crate::error::{AetherError, Result};
+use crate::repo::{find_repo_root, find_workspace_root};
+use crate::vcs::detect_vcs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, DocumentMut, ImDocument, Item, Table, TableLike, Value};
+
+/// Keys that configure layering rather than the environment.
+const LAYERING_KEYS: &[&str] = &["include", "profiles"];
+
+/// Spans of a key and its value within a layer's source.
+type Spans = (Option<Range<usize>>, Option<Range<usize>>);
+
+/// One config file, or a profile section of one.
+pub struct Layer {
+    pub file: PathBuf,
+    /// Key path of the layer within the file: empty, or `profiles.<name>`
+    pub prefix: Vec<String>,
+    document: ImDocument<String>,
+}
+
+impl Layer {
+    fn parse(file: &Path, source: String) -> std::result::Result<Self, Diagnostic> {
+        let label = file.display().to_string();
+        ImDocument::parse(source.clone())
+            .map(|document| Layer {
+                file: file.to_path_buf(),
+                prefix: Vec::new(),
+                document,
+            })
+            .map_err(|e| {
+                let offset = e.span().map_or(0, |s| s.start);
+                Diagnostic::at(&label, &source, offset, e.message().trim_end())
+            })
+    }
+
+    fn profile(&self, name: &str) -> Option<Layer> {
+        let prefix = vec!["profiles".to_string(), name.to_string()];
+        table_at(self.document.as_item(), &prefix)?;
+        Some(Layer {
+            file: self.file.clone(),
+            prefix,
+            document: self.document.clone(),
+        })
+    }
+
+    /// The layer's own table: the whole file, or its profile section.
+    fn root(&self) -> Option<&dyn TableLike> {
+        table_at(self.document.as_item(), &self.prefix)
+    }
+
+    /// `file` or `file [profiles.<name>]`.
+    pub fn label(&self) -> String {
+        if self.prefix.is_empty() {
+            self.file.display().to_string()
+        } else {
+            format!("{} [{}]", self.file.display(), self.prefix.join("."))
+        }
+    }
+
+    /// Key and value spans of `path` within the layer. Numeric segments
+    /// index into arrays.
+    fn spans(&self, path: &[String]) -> Option<Spans> {
+        let (last, parents) = path.split_last()?;
+        let mut table = self.root()?;
+        let mut array: Option<&Array> = None;
+
+        for segment in parents {
+            table = match array.take() {
+                Some(values) => values.get(segment.parse().ok()?)?.as_inline_table()?,
+                None => match table.get(segment)? {
+                    Item::Value(Value::Array(values)) => {
+                        array = Some(values);
+                        continue;
+                    }
+                    child => child.as_table_like()?,
+                },
+            };
+        }
+
+        match array {
+            Some(values) => Some((None, values.get(last.parse().ok()?)?.span())),
+            None => {
+                let (key, item) = table.get_key_value(last)?;
+                Some((key.span(), item.span()))
+            }
+        }
+    }
+
+    fn diagnostic(&self, offset: usize) -> Diagnostic {
+        Diagnostic::at(
+            &self.file.display().to_string(),
+            self.document.raw(),
+            offset,
+            "",
+        )
+    }
+}
+
+/// Table at key `path` below `item`.
+fn table_at<'a>(item: &'a Item, path: &[String]) -> Option<&'a dyn TableLike> {
+    let mut table = item.as_table_like()?;
+    for segment in path {
+        table = table.get(segment)?.as_table_like()?;
+    }
+    Some(table)
+}
+
+/// The merged layers of a config.
+pub struct ResolvedConfig {
+    /// Lowest precedence first
+    pub layers: Vec<Layer>,
+    /// The main `aether.toml`
+    pub main: PathBuf,
+    pub profile: Option<String>,
+    /// Merged document, without the layering keys, services expanded
+    pub document: DocumentMut,
+    /// Nodes of expanded services and the document paths they came from
+    pub aliases: Vec<Alias>,
+    /// Problems reading the layers themselves: syntax errors and includes
+    /// that match no files
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ResolvedConfig {
+    /// Deserialize the merged document.
+    pub fn config(&self) -> Result<AetherConfig> {
+        toml::from_str(&self.document.to_string())
+            .map_err(|e| AetherError::Config(format!("Failed to parse TOML: {}", e)))
+    }
+
+    /// The layer that set `path`, i.e. the last one defining it.
+    pub fn origin(&self, path: &[String]) -> Option<&Layer> {
+        let path = self.source_path(path);
+        self.layers
+            .iter()
+            .rev()
+            .find(|layer| layer.spans(&path).is_some())
+    }
+
+    /// Where `path` was written: the template or base service for values a
+    /// service inherited through `extends`, otherwise `path` itself.
+    fn source_path(&self, path: &[String]) -> Vec<String> {
+        let mut path = path.to_vec();
+        // A template's values may themselves come from a preset
+        for _ in 0..2 {
+            let Some((node, source)) = self
+                .aliases
+                .iter()
+                .filter(|(node, _)| path.starts_with(node))
+                .max_by_key(|(node, _)| node.len())
+            else {
+                break;
+            };
+            let mut mapped = source.clone();
+            mapped.extend(path[node.len()..].iter().cloned());
+            path = mapped;
+        }
+        path
+    }
+
+    /// Position `delta` bytes into the key (or value) at `path`, in the
+    /// layer that set it.
+    pub(super) fn locate(&self, path: &[String], key: bool, delta: usize) -> Option<Diagnostic> {
+        let layer = self.origin(path)?;
+        let (key_span, value_span) = layer.spans(&self.source_path(path))?;
+        let span = if key {
+            key_span.or(value_span)
+        } else {
+            value_span.or(key_span)
+        }?;
+        let offset = (span.start + delta).min(span.end.max(span.start));
+        Some(layer.diagnostic(offset))
+    }
+
+    /// Position `offset` in the main file, for problems with no better home.
+    pub(super) fn main_diagnostic(&self, offset: usize) -> Diagnostic {
+        self.layers
+            .iter()
+            .find(|layer| layer.file == self.main && layer.prefix.is_empty())
+            .map(|layer| layer.diagnostic(offset))
+            .unwrap_or_else(|| Diagnostic::at(&self.main.display().to_string(), "", 0, ""))
+    }
+
+    /// Every value of the merged document as a dotted key path, with the
+    /// `file:line:column` it came from.
+    pub fn origins(&self) -> Vec<(String, String)> {
+        let mut leaves = Vec::new();
+        collect_leaves(self.document.as_table(), &mut Vec::new(), &mut leaves);
+        leaves
+            .into_iter()
+            .map(|(path, _)| {
+                let origin = self
+                    .locate(&path, true, 0)
+                    .map(|d| format!("{}:{}:{}", d.file, d.line, d.column))
+                    .unwrap_or_default();
+                (path.join("."), origin)
+            })
+            .collect()
+    }
+}
+
+/// The user-global config, `$XDG_CONFIG_HOME/aether/config.toml` or
+/// `~/.config/aether/config.toml`.
+pub fn global_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(config_dir.join("aether/config.toml"))
+}
+
+/// `aether.local.toml` for `config_file`: next to it, or else at the same
+/// place in the main checkout, since ignored files are not part of new
+/// workspaces.
+pub fn local_config_path(config_file: &Path) -> Option<PathBuf> {
+    let file_name = format!("{}.local.toml", config_file.file_stem()?.to_str()?);
+    let local = config_file.with_file_name(&file_name);
+    if local.exists() {
+        return Some(local);
+    }
+
+    let dir = match config_file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let workspace_root = find_workspace_root(dir).ok()?;
+    let relative = dir.canonicalize().ok()?;
+    let relative = relative.strip_prefix(&workspace_root).ok()?;
+    let main = find_repo_root(&workspace_root)
+        .ok()?
+        .join(relative)
+        .join(file_name);
+    main.exists().then_some(main)
+}
+
+/// Whether `file` is under version control in the repository containing it.
+fn is_tracked(file: &Path) -> bool {
+    let dir = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    detect_vcs(dir).is_ok_and(|vcs| vcs.is_tracked(file))
+}
+
+/// Resolve the layers around the config file at `config_file`.
+pub fn resolve_config(config_file: &Path, profile: Option<&str>) -> Result<ResolvedConfig> {
+    let source = std::fs::read_to_string(config_file)
+        .map_err(|e| AetherError::Config(format!("Failed to read config: {}", e)))?;
+    let include_dir = config_file.parent().unwrap_or(Path::new("."));
+    resolve_layers(
+        config_file,
+        source,
+        include_dir,
+        global_config_path().as_deref(),
+        local_config_path(config_file).as_deref(),
+        profile,
+    )
+}
+
+/// Resolve the layers around `main`, whose contents are `source`. Includes
+/// are relative to `include_dir`.
+pub fn resolve_layers(
+    main: &Path,
+    source: String,
+    include_dir: &Path,
+    global: Option<&Path>,
+    local: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<ResolvedConfig> {
+    let mut layers = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    if let Some(global) = global.filter(|g| g.exists()) {
+        read_layer(global, &mut layers, &mut diagnostics);
+    }
+
+    match Layer::parse(main, source) {
+        Ok(main_layer) => {
+            for file in includes(&main_layer, include_dir, &mut diagnostics) {
+                read_layer(&file, &mut layers, &mut diagnostics);
+            }
+            layers.push(main_layer);
+        }
+        Err(diagnostic) => diagnostics.push(diagnostic),
+    }
+
+    if let Some(local) = local {
+        read_layer(local, &mut layers, &mut diagnostics);
+        if is_tracked(local) {
+            diagnostics.push(Diagnostic::at(
+                &local.display().to_string(),
+                "",
+                0,
+                "per-developer settings must not be committed; untrack the file \
+                 and add it to .gitignore",
+            ));
+        }
+    }
+
+    if let Some(name) = profile {
+        let sections: Vec<Layer> = layers.iter().filter_map(|l| l.profile(name)).collect();
+        if sections.is_empty() && diagnostics.is_empty() {
+            return Err(AetherError::Config(format!(
+                "Profile '{}' is not defined: add a [profiles.{}] table",
+                name, name
+            )));
+        }
+        layers.extend(sections);
+    }
+
+    let mut document = DocumentMut::new();
+    for layer in &layers {
+        let Some(root) = layer.root() else {
+            continue;
+        };
+        let mut leaves = Vec::new();
+        collect_leaves(root, &mut Vec::new(), &mut leaves);
+        for (path, item) in leaves {
+            if layer.prefix.is_empty() && LAYERING_KEYS.contains(&path[0].as_str()) {
+                continue;
+            }
+            set_at(document.as_table_mut(), &path, item);
+        }
+    }
+    let mut aliases = expand_presets(&mut document);
+    aliases.extend(expand_extends(&mut document));
+
+    Ok(ResolvedConfig {
+        layers,
+        main: main.to_path_buf(),
+        profile: profile.map(str::to_string),
+        document,
+        aliases,
+        diagnostics,
+    })
+}
+
+fn read_layer(file: &Path, layers: &mut Vec<Layer>, diagnostics: &mut Vec<Diagnostic>) {
+    match std::fs::read_to_string(file) {
+        Ok(source) => match Layer::parse(file, source) {
+            Ok(layer) => layers.push(layer),
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        },
+        Err(e) => diagnostics.push(Diagnostic::at(
+            &file.display().to_string(),
+            "",
+            0,
+            format!("Failed to read config: {}", e),
+        )),
+    }
+}
+
+/// Files matched by the `include` patterns of `layer`, sorted within each
+/// pattern. Patterns that match nothing are reported.
+fn includes(layer: &Layer, dir: &Path, diagnostics: &mut Vec<Diagnostic>) -> Vec<PathBuf> {
+    let Some(root) = layer.root() else {
+        return Vec::new();
+    };
+    let Some((key, item)) = root.get_key_value("include") else {
+        return Vec::new();
+    };
+    let report = |offset: Option<Range<usize>>, message: String| {
+        layer
+            .diagnostic(offset.map_or(0, |s| s.start))
+            .with_message(message)
+    };
+
+    let Some(patterns) = item.as_array() else {
+        diagnostics.push(report(
+            key.span(),
+            "`include` must be an array of glob patterns".to_string(),
+        ));
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    for pattern in patterns.iter() {
+        let Some(text) = pattern.as_str() else {
+            diagnostics.push(report(
+                pattern.span(),
+                "`include` patterns must be strings".to_string(),
+            ));
+            continue;
+        };
+        let full = dir.join(text).to_string_lossy().to_string();
+        let mut matched: Vec<PathBuf> = match glob::glob(&full) {
+            Ok(paths) => paths.filter_map(|p| p.ok()).collect(),
+            Err(e) => {
+                diagnostics.push(report(
+                    pattern.span(),
+                    format!("invalid include pattern `{}`: {}", text, e),
+                ));
+                continue;
+            }
+        };
+        if matched.is_empty() {
+            diagnostics.push(report(
+                pattern.span(),
+                format!("include pattern `{}` matches no files", text),
+            ));
+        }
+        matched.sort();
+        files.extend(matched);
+    }
+    files
+}
+
+/// Non-table values below `table` with their key paths. Inline tables are
+/// flattened so they merge like standard ones; empty tables are kept so
+/// they still exist after merging.
+fn collect_leaves(
+    table: &dyn TableLike,
+    path: &mut Vec<String>,
+    out: &mut Vec<(Vec<String>, Item)>,
+) {
+    for (key, item) in table.iter() {
+        path.push(key.to_string());
+        match item.as_table_like() {
+            Some(child) if !child.is_empty() => collect_leaves(child, path, out),
+            Some(_) => out.push((path.clone(), Item::Table(Table::new()))),
+            None => out.push((path.clone(), item.clone())),
+        }
+        path.pop();
+    }
+}
+
+fn set_at(root: &mut Table, path: &[String], mut item: Item) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut table = root;
+    for segment in parents {
+        let entry = table.entry(segment).or_insert_with(|| {
+            let mut implicit = Table::new();
+            implicit.set_implicit(true);
+            Item::Table(implicit)
+        });
+        if !entry.is_table() {
+            *entry = Item::Table(Table::new());
+        }
+        table = entry.as_table_mut().expect("just made a table");
+    }
+
+    if item.is_table() {
+        if !table.get(last).is_some_and(Item::is_table) {
+            table.insert(last, item);
+        }
+        return;
+    }
+    if let Some(value) = item.as_value_mut() {
+        value.decor_mut().clear();
+    }
+    table.insert(last, item);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn resolve(dir: &Path, global: Option<&Path>, profile: Option<&str>) -> ResolvedConfig {
+        let main = dir.join("aether.toml");
+        let source = fs::read_to_string(&main).unwrap();
+        let local = dir.join("aether.local.toml");
+        let local = local.exists().then_some(local);
+        resolve_layers(&main, source, dir, global, local.as_deref(), profile).unwrap()
+    }
+
+    #[test]
+    fn test_layers_merge_in_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::write(
+            dir.join("global.toml"),
+            "[backend]\ntype = \"docker\"\nsocket = \"/run/user/docker.sock\"\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.join("infra")).unwrap();
+        fs::write(
+            dir.join("infra/redis.toml"),
+            "[services.redis]\nimage = \"redis:7\"\nports = [\"6379\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("aether.toml"),
+            r#"include = ["infra/*.toml"]
+
+[backend]
+type = "docker"
+
+[services.postgres]
+image = "postgres:15"
+ports = ["5432"]
+env = { POSTGRES_USER = "app", POSTGRES_PASSWORD = "devpass" }
+
+[profiles.full.services.kafka]
+image = "kafka:3"
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("aether.local.toml"),
+            "[services.postgres]\nimage = \"postgres:16\"\nenv.POSTGRES_PASSWORD = \"mine\"\n",
+        )
+        .unwrap();
+
+        let resolved = resolve(dir, Some(&dir.join("global.toml")), None);
+        assert!(resolved.diagnostics.is_empty());
+        let config = resolved.config().unwrap();
+        let postgres = &config.services["postgres"];
+        assert_eq!(postgres.image, "postgres:16");
+        assert_eq!(postgres.env["POSTGRES_USER"], "app");
+        assert_eq!(postgres.env["POSTGRES_PASSWORD"], "mine");
+        assert!(config.services.contains_key("redis"));
+        assert!(!config.services.contains_key("kafka"));
+        assert!(matches!(
+            config.backend,
+            crate::config::BackendConfig::Docker { socket: Some(_) }
+        ));
+
+        let origins = resolved.origins();
+        let origin = |key: &str| {
+            origins
+                .iter()
+                .find(|(path, _)| path == key)
+                .map(|(_, origin)| origin.clone())
+                .unwrap()
+        };
+        assert!(origin("services.postgres.image").ends_with("aether.local.toml:2:1"));
+        assert!(origin("services.postgres.ports").ends_with("aether.toml:8:1"));
+        assert!(origin("services.redis.image").ends_with("redis.toml:2:1"));
+        assert!(origin("backend.socket").ends_with("global.toml:3:1"));
+
+        let full = resolve(dir, None, Some("full"));
+        assert!(full.config().unwrap().services.contains_key("kafka"));
+        assert!(full.origins().iter().any(|(path, origin)| {
+            path == "services.kafka.image" && origin.ends_with("aether.toml:12:1")
+        }));
+    }
+
+    #[test]
+    fn test_unknown_profile_and_missing_include() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::write(
+            dir.join("aether.toml"),
+            "include = [\"infra/*.toml\"]\n[backend]\ntype = \"docker\"\n",
+        )
+        .unwrap();
+
+        let resolved = resolve(dir, None, None);
+        assert_eq!(resolved.diagnostics.len(), 1);
+        assert_eq!(
+            (resolved.diagnostics[0].line, resolved.diagnostics[0].column),
+            (1, 12)
+        );
+
+        fs::write(dir.join("aether.toml"), "[backend]\ntype = \"docker\"\n").unwrap();
+        let source = fs::read_to_string(dir.join("aether.toml")).unwrap();
+        assert!(resolve_layers(
+            &dir.join("aether.toml"),
+            source,
+            dir,
+            None,
+            None,
+            Some("minimal")
+        )
+        .is_err());
+    }
+}
@@ -1,4 +1,7 @@
+use super::interpolate::{env_lookup, interpolate};
+use super::layers::{resolve_config, ResolvedConfig};
 use super::schema::AetherConfig;
+use super::validate::{diagnostics_error, validate_resolved};
 use crate::error::{AetherError, Result};
 use std::path::{Path, PathBuf};
 
@@ -11,8 +14,8 @@ pub fn find_config(start_path: &Path) -> Result<PathBuf> {
             return Ok(candidate);
         }
 
-        // Stop at repo root (.jj directory)
-        if current.join(".jj").is_dir() {
+        // Stop at the workspace root (.jj directory or .git entry)
+        if current.join(".jj").is_dir() || current.join(".git").exists() {
             break;
         }
 
@@ -25,12 +28,66 @@ pub fn find_config(start_path: &Path) -> Result<PathBuf> {
     Err(AetherError::Config("aether.toml not found in repo".into()))
 }
 
-pub fn load_config(path: &Path) -> Result<AetherConfig> {
-    let content = std::fs::read_to_string(path)
-        .map_err(|e| AetherError::Config(format!("Failed to read config: {}", e)))?;
+/// Load the config rooted at `path` with its layers merged and `profile`
+/// applied, rejecting it if validation finds any problem.
+pub fn load_config(path: &Path, profile: Option<&str>) -> Result<AetherConfig> {
+    load_resolved(&resolve_config(path, profile)?)
+}
+
+/// Validate, deserialize and interpolate already resolved layers.
+pub fn load_resolved(resolved: &ResolvedConfig) -> Result<AetherConfig> {
+    let diagnostics = validate_resolved(resolved);
+    if !diagnostics.is_empty() {
+        return Err(diagnostics_error(&diagnostics));
+    }
 
-    toml::from_str(&content)
-        .map_err(|e| AetherError::Config(format!("Failed to parse TOML: {}", e)))
+    let mut config = resolved.config()?;
+    interpolate_config(&mut config, &env_lookup)?;
+    Ok(config)
+}
+
+/// Expand `${VAR}` references in service images, env values, volumes,
+/// commands, entrypoints, working directories, env files and labels, and in
+/// the injection template.
+pub fn interpolate_config(
+    config: &mut AetherConfig,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<()> {
+    let expand = |value: &mut String, what: &str| -> Result<()> {
+        *value = interpolate(value, lookup)
+            .map_err(|e| AetherError::Config(format!("{}: {}", what, e)))?;
+        Ok(())
+    };
+
+    for (name, service) in config.services.iter_mut() {
+        expand(&mut service.image, &format!("services.{}.image", name))?;
+        for (key, value) in service.env.iter_mut() {
+            expand(value, &format!("services.{}.env.{}", name, key))?;
+        }
+        for volume in service.volumes.iter_mut() {
+            expand(volume, &format!("services.{}.volumes", name))?;
+        }
+        for arg in service.command.iter_mut().flatten() {
+            expand(arg, &format!("services.{}.command", name))?;
+        }
+        for arg in service.entrypoint.iter_mut().flatten() {
+            expand(arg, &format!("services.{}.entrypoint", name))?;
+        }
+        if let Some(working_dir) = service.working_dir.as_mut() {
+            expand(working_dir, &format!("services.{}.working_dir", name))?;
+        }
+        for env_file in service.env_file.iter_mut() {
+            expand(env_file, &format!("services.{}.env_file", name))?;
+        }
+        for (key, value) in service.labels.iter_mut() {
+            expand(value, &format!("services.{}.labels.{}", name, key))?;
+        }
+    }
+    if let Some(injection) = config.injection.as_mut() {
+        expand(&mut injection.template, "injection.template")?;
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -55,10 +112,65 @@ mod tests {
         let config_path = temp_dir.path().join("aether.toml");
         fs::write(&config_path, "[backend]\ntype = \"docker\"").unwrap();
 
-        let config = load_config(&config_path).unwrap();
+        let config = load_config(&config_path, None).unwrap();
         assert!(matches!(
             config.backend,
             super::super::schema::BackendConfig::Docker { .. }
         ));
     }
+
+    #[test]
+    fn test_interpolate_config() {
+        let mut config: AetherConfig = toml::from_str(
+            r#"
+[backend]
+type = "docker"
+
+[services.postgres]
+image = "postgres:${PG_VERSION:-15}"
+env = { POSTGRES_PASSWORD = "${DB_PASSWORD}" }
+volumes = ["${HOME}/data:/var/lib/postgresql/data"]
+
+[injection]
+file = ".env"
+template = "DB_PASSWORD=${DB_PASSWORD}"
+"#,
+        )
+        .unwrap();
+        let lookup = |name: &str| match name {
+            "DB_PASSWORD" => Some("s3cret".to_string()),
+            "HOME" => Some("/home/dev".to_string()),
+            _ => None,
+        };
+
+        interpolate_config(&mut config, &lookup).unwrap();
+        let postgres = &config.services["postgres"];
+        assert_eq!(postgres.image, "postgres:15");
+        assert_eq!(postgres.env["POSTGRES_PASSWORD"], "s3cret");
+        assert_eq!(
+            postgres.volumes[0],
+            "/home/dev/data:/var/lib/postgresql/data"
+        );
+        assert_eq!(config.injection.unwrap().template, "DB_PASSWORD=s3cret");
+
+        let mut config: AetherConfig =
+            toml::from_str("[backend]\ntype = \"docker\"\n[services.app]\nimage = \"${IMAGE}\"\n")
+                .unwrap();
+        let err = interpolate_config(&mut config, &|_| None).unwrap_err();
+        assert!(err.to_string().contains("services.app.image"));
+    }
+
+    #[test]
+    fn test_load_config_reports_diagnostics() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("aether.toml");
+        fs::write(
+            &config_path,
+            "[backend]\ntype = \"docker\"\n\n[services.pg]\nimage = \"postgres\"\nports = [\"x\"]\n",
+        )
+        .unwrap();
+
+        let err = load_config(&config_path, None).unwrap_err().to_string();
+        assert!(err.contains(&format!("{}:6:10: invalid port `x`", config_path.display())));
+    }
 }
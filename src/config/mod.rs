@@ -1,5 +1,21 @@
+pub mod compose;
+pub mod extends;
+pub mod interpolate;
+pub mod json_schema;
+pub mod layers;
 pub mod loader;
+pub mod presets;
+pub mod scaffold;
 pub mod schema;
+pub mod validate;
 
+pub use compose::*;
+pub use extends::*;
+pub use interpolate::*;
+pub use json_schema::*;
+pub use layers::*;
 pub use loader::*;
+pub use presets::*;
+pub use scaffold::*;
 pub use schema::*;
+pub use validate::*;
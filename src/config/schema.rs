@@ -1,7 +1,11 @@
+use crate::error::{AetherError, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct AetherConfig {
     pub backend: BackendConfig,
     #[serde(default)]
@@ -9,22 +13,34 @@ pub struct AetherConfig {
     pub injection: Option<InjectionConfig>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 #[serde(tag = "type", rename_all = "lowercase")]
+#[schemars(deny_unknown_fields)]
 pub enum BackendConfig {
     Docker {
+        /// Docker socket to connect to instead of the default
         #[serde(default)]
         socket: Option<String>,
     },
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct ServiceConfig {
+    /// Template or service to start from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Built-in preset to start from, see [`crate::config::presets`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
     pub image: String,
+    /// Container ports, each published on a free host port
     #[serde(default)]
+    #[schemars(inner(regex(pattern = r"^[0-9]+$")))]
     pub ports: Vec<String>,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// `source:target[:mode]`; named volumes are scoped to the workspace
     #[serde(default)]
     pub volumes: Vec<String>,
     #[serde(default)]
@@ -33,23 +49,211 @@ pub struct ServiceConfig {
     pub depends_on: Vec<String>,
     #[serde(default)]
     pub resources: Option<ResourceLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub healthcheck: Option<HealthcheckConfig>,
+    /// Replaces the image's entrypoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    /// `user`, `user:group` or numeric ids
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Files of `KEY=value` lines relative to the workspace root; `env`
+    /// takes precedence over them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_file: Vec<String>,
+    /// `host:ip` entries added to `/etc/hosts`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_hosts: Vec<String>,
+    /// `/path` or `/path:options` tmpfs mounts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tmpfs: Vec<String>,
+    /// Size of `/dev/shm`, such as `256m`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = MEMORY_PATTERN))]
+    pub shm_size: Option<String>,
+    /// e.g. `nofile = { soft = 1024, hard = 65536 }` or `nproc = 65535`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub ulimits: HashMap<String, Ulimit>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cap_add: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cap_drop: Vec<String>,
+    /// Container labels; `aether.*` labels are reserved
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels: HashMap<String, String>,
+    /// `no`, `always`, `unless-stopped`, `on-failure` or `on-failure:<retries>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = RESTART_PATTERN))]
+    pub restart: Option<String>,
+    /// How long to wait after the stop signal before killing, such as `30s`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = DURATION_PATTERN))]
+    pub stop_grace_period: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// A resource limit: one number for both limits, or separate soft and
+/// hard limits.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(untagged)]
+#[schemars(deny_unknown_fields)]
+pub enum Ulimit {
+    Single(i64),
+    Pair { soft: i64, hard: i64 },
+}
+
+impl Ulimit {
+    /// The soft and hard limit.
+    pub fn limits(&self) -> (i64, i64) {
+        match *self {
+            Ulimit::Single(limit) => (limit, limit),
+            Ulimit::Pair { soft, hard } => (soft, hard),
+        }
+    }
+}
+
+/// A container healthcheck, as in compose: `test` is `["CMD", ...]` or
+/// `["CMD-SHELL", "..."]`, durations are strings such as `5s` or `1m30s`.
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct HealthcheckConfig {
+    pub test: Vec<String>,
+    #[serde(default)]
+    #[schemars(regex(pattern = DURATION_PATTERN))]
+    pub interval: Option<String>,
+    #[serde(default)]
+    #[schemars(regex(pattern = DURATION_PATTERN))]
+    pub timeout: Option<String>,
+    #[serde(default)]
+    pub retries: Option<u32>,
+    #[serde(default)]
+    #[schemars(regex(pattern = DURATION_PATTERN))]
+    pub start_period: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct ResourceLimits {
+    /// CPUs, e.g. `1.5`
     #[serde(default)]
     pub cpu_limit: Option<f64>,
     #[serde(default)]
     pub cpu_reservation: Option<f64>,
+    /// Memory such as `512m` or `1g`
     #[serde(default)]
+    #[schemars(regex(pattern = MEMORY_PATTERN))]
     pub memory_limit: Option<String>,
     #[serde(default)]
+    #[schemars(regex(pattern = MEMORY_PATTERN))]
     pub memory_reservation: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// What [`parse_memory_to_bytes`] accepts, for the JSON Schema.
+const MEMORY_PATTERN: &str = r"^\s*[0-9.]+\s*([kKmMgG][bB]?)\s*$";
+/// What [`parse_duration`] accepts, for the JSON Schema.
+const DURATION_PATTERN: &str = r"^\s*([0-9.]+(ms|s|m|h))+\s*$";
+/// What [`parse_restart`] accepts, for the JSON Schema.
+const RESTART_PATTERN: &str = r"^(no|always|unless-stopped|on-failure(:[0-9]+)?)$";
+
+/// Parse a memory quantity such as `512m`, `1g` or `256kb` to bytes.
+pub fn parse_memory_to_bytes(mem: &str) -> Result<i64> {
+    let mem = mem.trim().to_lowercase();
+    let split = mem
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(mem.len());
+    let (num_str, unit) = mem.split_at(split);
+
+    let num: f64 = num_str
+        .parse()
+        .map_err(|_| AetherError::Config(format!("Invalid memory value: {}", mem)))?;
+    if num <= 0.0 {
+        return Err(AetherError::Config(format!(
+            "Memory value must be positive: {}",
+            mem
+        )));
+    }
+
+    let multiplier = match unit {
+        "k" | "kb" => 1024.0,
+        "m" | "mb" => 1024.0 * 1024.0,
+        "g" | "gb" => 1024.0 * 1024.0 * 1024.0,
+        _ => {
+            return Err(AetherError::Config(format!(
+                "Invalid memory unit: {} (expected k, m or g)",
+                unit
+            )))
+        }
+    };
+
+    Ok((num * multiplier) as i64)
+}
+
+/// Parse a duration such as `500ms`, `10s`, `1m30s` or `2h`.
+pub fn parse_duration(duration: &str) -> Result<Duration> {
+    let invalid = || AetherError::Config(format!("Invalid duration: {}", duration));
+    let mut rest = duration.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or_else(invalid)?;
+        let num: f64 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let seconds = match &rest[..unit_len] {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            unit => {
+                return Err(AetherError::Config(format!(
+                    "Invalid duration unit: {} (expected ms, s, m or h)",
+                    unit
+                )))
+            }
+        };
+        total += Duration::from_secs_f64(num * seconds);
+        rest = &rest[unit_len..];
+    }
+
+    Ok(total)
+}
+
+/// Parse a restart policy such as `unless-stopped` or `on-failure:3` into
+/// the policy name and the maximum number of retries.
+pub fn parse_restart(restart: &str) -> Result<(&str, Option<u32>)> {
+    let invalid = || {
+        AetherError::Config(format!(
+            "Invalid restart policy: {} (expected no, always, unless-stopped, on-failure or on-failure:<retries>)",
+            restart
+        ))
+    };
+    match restart.split_once(':') {
+        Some(("on-failure", retries)) => {
+            Ok(("on-failure", Some(retries.parse().map_err(|_| invalid())?)))
+        }
+        None if ["no", "always", "unless-stopped", "on-failure"].contains(&restart) => {
+            Ok((restart, None))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct InjectionConfig {
+    /// File written in each workspace, relative to its root
     pub file: String,
+    /// Handlebars template rendered with the `services` context
     pub template: String,
 }
 
@@ -76,4 +280,57 @@ template = "DB_PORT={{ services.postgres.ports.5432 }}"
         assert_eq!(config.services.len(), 1);
         assert!(config.injection.is_some());
     }
+
+    #[test]
+    fn test_parse_memory_to_bytes() {
+        assert_eq!(parse_memory_to_bytes("512m").unwrap(), 512 * 1024 * 1024);
+        assert_eq!(parse_memory_to_bytes("1GB").unwrap(), 1024 * 1024 * 1024);
+        assert_eq!(parse_memory_to_bytes("1.5k").unwrap(), 1536);
+        assert!(parse_memory_to_bytes("512").is_err());
+        assert!(parse_memory_to_bytes("0m").is_err());
+        assert!(parse_memory_to_bytes("").is_err());
+        assert!(parse_memory_to_bytes("lots").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("10s").unwrap(), Duration::from_secs(10));
+        assert_eq!(parse_duration("1m30s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("1.5h").unwrap(), Duration::from_secs(5400));
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration("10d").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn test_parse_restart() {
+        assert_eq!(
+            parse_restart("unless-stopped").unwrap(),
+            ("unless-stopped", None)
+        );
+        assert_eq!(
+            parse_restart("on-failure:3").unwrap(),
+            ("on-failure", Some(3))
+        );
+        assert!(parse_restart("always:3").is_err());
+        assert!(parse_restart("on-failure:many").is_err());
+        assert!(parse_restart("sometimes").is_err());
+    }
+
+    #[test]
+    fn test_parse_container_options() {
+        let service: ServiceConfig = toml::from_str(
+            r#"
+image = "elasticsearch:8.13.0"
+entrypoint = ["/bin/tini", "--"]
+ulimits = { nofile = { soft = 1024, hard = 65536 }, memlock = -1 }
+restart = "on-failure:3"
+"#,
+        )
+        .unwrap();
+        assert_eq!(service.ulimits["nofile"].limits(), (1024, 65536));
+        assert_eq!(service.ulimits["memlock"].limits(), (-1, -1));
+        assert_eq!(service.entrypoint.unwrap()[0], "/bin/tini");
+    }
 }
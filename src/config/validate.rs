@@ -0,0 +1,1067 @@
+//! Validation of `aether.toml` beyond what deserialization checks, with
+//! every problem located by file, line and column.
+
+use super::extends::base_path;
+use super::interpolate::{env_lookup, interpolate};
+use super::layers::{resolve_config, resolve_layers, ResolvedConfig};
+use super::presets::{find_preset, PRESETS};
+use super::schema::{parse_duration, parse_memory_to_bytes, parse_restart, AetherConfig};
+use crate::error::{AetherError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use toml_edit::{ImDocument, Item, TableLike};
+
+const TOP_LEVEL_KEYS: &[&str] = &[
+    "backend",
+    "services",
+    "templates",
+    "injection",
+    "include",
+    "profiles",
+];
+const BACKEND_KEYS: &[&str] = &["type", "socket"];
+const SERVICE_KEYS: &[&str] = &[
+    "extends",
+    "preset",
+    "image",
+    "ports",
+    "env",
+    "volumes",
+    "command",
+    "depends_on",
+    "resources",
+    "healthcheck",
+    "entrypoint",
+    "working_dir",
+    "user",
+    "hostname",
+    "env_file",
+    "extra_hosts",
+    "tmpfs",
+    "shm_size",
+    "ulimits",
+    "cap_add",
+    "cap_drop",
+    "labels",
+    "restart",
+    "stop_grace_period",
+];
+const RESOURCE_KEYS: &[&str] = &[
+    "cpu_limit",
+    "cpu_reservation",
+    "memory_limit",
+    "memory_reservation",
+];
+const HEALTHCHECK_KEYS: &[&str] = &["test", "interval", "timeout", "retries", "start_period"];
+const ULIMIT_KEYS: &[&str] = &["soft", "hard"];
+const INJECTION_KEYS: &[&str] = &["file", "template"];
+/// Service keys whose strings get `${VAR}` expansion
+const INTERPOLATED_KEYS: &[&str] = &[
+    "image",
+    "env",
+    "volumes",
+    "command",
+    "entrypoint",
+    "working_dir",
+    "env_file",
+    "labels",
+];
+
+/// A problem in a config file, reported as `file:line:column: message`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub file: String,
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file, self.line, self.column, self.message
+        )
+    }
+}
+
+/// Validate the layered config rooted at `path`, with `profile` applied.
+pub fn validate_config(path: &Path, profile: Option<&str>) -> Result<Vec<Diagnostic>> {
+    Ok(validate_resolved(&resolve_config(path, profile)?))
+}
+
+/// Validate config `source` on its own, without other layers, attributing
+/// diagnostics to `file`. Returns every problem found, sorted by position;
+/// empty means the config is valid.
+pub fn validate_source(source: &str, file: &str) -> Vec<Diagnostic> {
+    let file = Path::new(file);
+    let include_dir = file.parent().unwrap_or(Path::new("."));
+    match resolve_layers(file, source.to_string(), include_dir, None, None, None) {
+        Ok(resolved) => validate_resolved(&resolved),
+        Err(e) => vec![Diagnostic::at(
+            &file.display().to_string(),
+            source,
+            0,
+            e.to_string(),
+        )],
+    }
+}
+
+/// Validate merged layers. Problems are found in the merged document and
+/// reported at the layer that set the offending key or value.
+pub fn validate_resolved(resolved: &ResolvedConfig) -> Vec<Diagnostic> {
+    let source = resolved.document.to_string();
+    let mut diagnostics = resolved.diagnostics.clone();
+
+    // Without the main file, the merged document is missing too much to check
+    let main_parsed = resolved.layers.iter().any(|l| l.file == resolved.main);
+    if let Some(merged) = ImDocument::parse(source.as_str())
+        .ok()
+        .filter(|_| main_parsed)
+    {
+        for (offset, message) in check_source(&source) {
+            let diagnostic = match path_at(merged.as_item(), offset) {
+                Some(anchor) => resolved.locate(&anchor.path, anchor.key, offset - anchor.start),
+                None => None,
+            };
+            diagnostics.push(
+                diagnostic
+                    .unwrap_or_else(|| resolved.main_diagnostic(0))
+                    .with_message(message),
+            );
+        }
+    }
+
+    sort_diagnostics(&mut diagnostics);
+    diagnostics
+}
+
+fn sort_diagnostics(diagnostics: &mut Vec<Diagnostic>) {
+    diagnostics.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    diagnostics.dedup();
+}
+
+/// Render diagnostics as a config error, one per line.
+pub fn diagnostics_error(diagnostics: &[Diagnostic]) -> AetherError {
+    let lines: Vec<String> = diagnostics.iter().map(|d| format!("  {}", d)).collect();
+    AetherError::Config(format!("Invalid configuration:\n{}", lines.join("\n")))
+}
+
+impl Diagnostic {
+    /// A diagnostic at byte `offset` of `source`.
+    pub fn at(file: &str, source: &str, offset: usize, message: impl Into<String>) -> Self {
+        let before = &source[..offset.min(source.len())];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Diagnostic {
+            file: file.to_string(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+
+    pub(super) fn with_message(mut self, message: String) -> Self {
+        self.message = message;
+        self
+    }
+}
+
+/// Problems in `source` as byte offsets and messages.
+fn check_source(source: &str) -> Vec<(usize, String)> {
+    let mut validator = Validator {
+        source,
+        findings: Vec::new(),
+    };
+
+    match ImDocument::parse(source) {
+        Ok(document) => {
+            validator.check_document(document.as_item());
+            // Types and required keys are what deserialization checks
+            if let Err(e) = toml::from_str::<AetherConfig>(source) {
+                validator.report(e.span(), e.message());
+            }
+        }
+        Err(e) => validator.report(e.span(), e.message().trim_end()),
+    }
+
+    validator.findings
+}
+
+/// Where in a document an offset falls: the key path of the innermost key
+/// or value containing it, and the start of that key or value.
+struct Anchor {
+    path: Vec<String>,
+    /// Whether the offset is in the key rather than the value
+    key: bool,
+    start: usize,
+}
+
+fn contains(span: &Option<Range<usize>>, offset: usize) -> bool {
+    span.as_ref()
+        .is_some_and(|s| s.start <= offset && offset < s.end.max(s.start + 1))
+}
+
+fn path_at(item: &Item, offset: usize) -> Option<Anchor> {
+    fn in_value(value: &toml_edit::Value, offset: usize, path: &mut Vec<String>) -> Option<Anchor> {
+        match value {
+            toml_edit::Value::InlineTable(table) => in_table(table, offset, path),
+            toml_edit::Value::Array(array) => array.iter().enumerate().find_map(|(i, element)| {
+                path.push(i.to_string());
+                let found = in_value(element, offset, path);
+                path.pop();
+                found
+            }),
+            _ => None,
+        }
+        .or_else(|| {
+            let span = value.span();
+            contains(&span, offset).then(|| Anchor {
+                path: path.clone(),
+                key: false,
+                start: span.map_or(0, |s| s.start),
+            })
+        })
+    }
+
+    fn in_table(table: &dyn TableLike, offset: usize, path: &mut Vec<String>) -> Option<Anchor> {
+        for (name, _) in table.iter() {
+            let Some((key, child)) = table.get_key_value(name) else {
+                continue;
+            };
+            path.push(name.to_string());
+            let key_span = key.span();
+            let found = if contains(&key_span, offset) {
+                Some(Anchor {
+                    path: path.clone(),
+                    key: true,
+                    start: key_span.map_or(0, |s| s.start),
+                })
+            } else {
+                in_item(child, offset, path)
+            };
+            path.pop();
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    fn in_item(item: &Item, offset: usize, path: &mut Vec<String>) -> Option<Anchor> {
+        match item {
+            Item::Table(table) => in_table(table, offset, path),
+            Item::Value(value) => in_value(value, offset, path),
+            _ => None,
+        }
+    }
+
+    in_item(item, offset, &mut Vec::new())
+}
+
+/// Template variables a service provides besides `container_id`: its
+/// ports, and its preset's values.
+struct Declared {
+    ports: Vec<String>,
+    values: Vec<&'static str>,
+}
+
+struct Validator<'a> {
+    source: &'a str,
+    findings: Vec<(usize, String)>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, span: Option<Range<usize>>, message: impl Into<String>) {
+        let offset = span.map(|s| s.start).unwrap_or(0);
+        self.findings.push((offset, message.into()));
+    }
+
+    fn check_keys(&mut self, table: &dyn TableLike, allowed: &[&str], context: &str) {
+        for (key, _) in table.iter() {
+            if !allowed.contains(&key) {
+                let span = table.get_key_value(key).and_then(|(k, _)| k.span());
+                self.report(span, format!("unknown key `{}` in {}", key, context));
+            }
+        }
+    }
+
+    fn check_document(&mut self, root: &Item) {
+        let Some(root) = root.as_table_like() else {
+            return;
+        };
+        self.check_keys(root, TOP_LEVEL_KEYS, "the top level");
+
+        if let Some(backend) = root.get("backend").and_then(Item::as_table_like) {
+            self.check_keys(backend, BACKEND_KEYS, "[backend]");
+        }
+
+        let templates = root.get("templates").and_then(Item::as_table);
+        if let Some(templates) = templates {
+            for (name, template) in templates.iter() {
+                let Some(template) = template.as_table_like() else {
+                    continue;
+                };
+                self.check_keys(template, SERVICE_KEYS, &format!("[templates.{}]", name));
+                let services = root.get("services").and_then(Item::as_table);
+                if let Some(extends) = template.get("extends") {
+                    let base = extends.as_str().unwrap_or_default();
+                    if base_path(Some(templates), services, base).is_none() {
+                        self.report(
+                            extends.span(),
+                            format!(
+                                "template `{}` extends unknown template or service `{}`",
+                                name, base
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+
+        let services = root.get("services").and_then(Item::as_table_like);
+        let mut declared: BTreeMap<String, Declared> = BTreeMap::new();
+        if let Some(services) = services {
+            for (name, service) in services.iter() {
+                if let Some(service) = service.as_table_like() {
+                    let variables = self.check_service(name, service);
+                    declared.insert(name.to_string(), variables);
+                }
+            }
+            self.check_dependencies(services);
+        }
+        if let Some(services) = root.get("services").and_then(Item::as_table) {
+            self.check_extends(templates, services);
+        }
+
+        if let Some(injection) = root.get("injection").and_then(Item::as_table_like) {
+            self.check_keys(injection, INJECTION_KEYS, "[injection]");
+            if let Some(template) = injection.get("template") {
+                self.check_interpolation(template);
+                self.check_template(template, &declared);
+            }
+        }
+    }
+
+    /// Check one `[services.<name>]` table, returning the template
+    /// variables it provides.
+    fn check_service(&mut self, name: &str, service: &dyn TableLike) -> Declared {
+        let context = format!("[services.{}]", name);
+        self.check_keys(service, SERVICE_KEYS, &context);
+        for key in INTERPOLATED_KEYS {
+            if let Some(item) = service.get(key) {
+                self.check_interpolation(item);
+            }
+        }
+
+        let mut declared = Vec::new();
+        if let Some(ports) = service.get("ports").and_then(Item::as_array) {
+            for port in ports.iter() {
+                let Some(raw) = port.as_str() else {
+                    continue;
+                };
+                match raw.parse::<u16>() {
+                    Ok(0) | Err(_) => self.report(
+                        port.span(),
+                        format!(
+                            "invalid port `{}` in {}: expected a number from 1 to 65535",
+                            raw, context
+                        ),
+                    ),
+                    Ok(number) => {
+                        if declared.contains(&number.to_string()) {
+                            self.report(
+                                port.span(),
+                                format!("port {} is declared twice in {}", number, context),
+                            );
+                        } else {
+                            declared.push(number.to_string());
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(resources) = service.get("resources").and_then(Item::as_table_like) {
+            self.check_resources(resources, &format!("[services.{}.resources]", name));
+        }
+        if let Some(healthcheck) = service.get("healthcheck").and_then(Item::as_table_like) {
+            self.check_healthcheck(healthcheck, &format!("[services.{}.healthcheck]", name));
+        }
+        self.check_container_options(service, &context);
+
+        let mut values = Vec::new();
+        if let Some(preset) = service.get("preset") {
+            match preset.as_str().map(|p| (p, find_preset(p))) {
+                Some((_, Some(preset))) => values = preset.value_names(),
+                Some((unknown, None)) => {
+                    let known: Vec<&str> = PRESETS.iter().map(|p| p.name).collect();
+                    self.report(
+                        preset.span(),
+                        format!(
+                            "unknown preset `{}` in {} (expected one of: {})",
+                            unknown,
+                            context,
+                            known.join(", ")
+                        ),
+                    );
+                }
+                None => {}
+            }
+        }
+
+        Declared {
+            ports: declared,
+            values,
+        }
+    }
+
+    fn check_healthcheck(&mut self, healthcheck: &dyn TableLike, context: &str) {
+        self.check_keys(healthcheck, HEALTHCHECK_KEYS, context);
+        for key in ["interval", "timeout", "start_period"] {
+            let Some(item) = healthcheck.get(key) else {
+                continue;
+            };
+            if let Some(Err(AetherError::Config(e))) = item.as_str().map(parse_duration) {
+                self.report(item.span(), format!("{} in {}: {}", key, context, e));
+            }
+        }
+    }
+
+    fn check_container_options(&mut self, service: &dyn TableLike, context: &str) {
+        if let Some(item) = service.get("shm_size") {
+            if let Some(Err(AetherError::Config(e))) = item.as_str().map(parse_memory_to_bytes) {
+                self.report(item.span(), format!("shm_size in {}: {}", context, e));
+            }
+        }
+        if let Some(item) = service.get("stop_grace_period") {
+            if let Some(Err(AetherError::Config(e))) = item.as_str().map(parse_duration) {
+                self.report(
+                    item.span(),
+                    format!("stop_grace_period in {}: {}", context, e),
+                );
+            }
+        }
+        if let Some(item) = service.get("restart") {
+            if let Some(Err(AetherError::Config(e))) = item.as_str().map(parse_restart) {
+                self.report(item.span(), format!("restart in {}: {}", context, e));
+            }
+        }
+
+        if let Some(ulimits) = service.get("ulimits").and_then(Item::as_table_like) {
+            for (name, ulimit) in ulimits.iter() {
+                let Some(ulimit) = ulimit.as_table_like() else {
+                    continue;
+                };
+                let ulimit_context = format!("ulimit `{}` of {}", name, context);
+                self.check_keys(ulimit, ULIMIT_KEYS, &ulimit_context);
+                let limit = |key| ulimit.get(key).and_then(Item::as_integer);
+                if let (Some(soft), Some(hard)) = (limit("soft"), limit("hard")) {
+                    if hard >= 0 && (soft < 0 || soft > hard) {
+                        let span = ulimit.get("soft").and_then(Item::span);
+                        self.report(
+                            span,
+                            format!("soft limit exceeds hard limit in {}", ulimit_context),
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Some(labels) = service.get("labels").and_then(Item::as_table_like) {
+            for (key, _) in labels.iter() {
+                if key.starts_with("aether.") {
+                    let span = labels.get_key_value(key).and_then(|(k, _)| k.span());
+                    self.report(
+                        span,
+                        format!("label `{}` in {} is reserved for Aether", key, context),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_resources(&mut self, resources: &dyn TableLike, context: &str) {
+        self.check_keys(resources, RESOURCE_KEYS, context);
+
+        let mut cpus = [None, None];
+        for (slot, key) in ["cpu_limit", "cpu_reservation"].iter().enumerate() {
+            let Some(item) = resources.get(key) else {
+                continue;
+            };
+            let Some(cpu) = item.as_float().or(item.as_integer().map(|i| i as f64)) else {
+                continue;
+            };
+            if cpu > 0.0 {
+                cpus[slot] = Some(cpu);
+            } else {
+                self.report(
+                    item.span(),
+                    format!("{} in {} must be greater than 0", key, context),
+                );
+            }
+        }
+        if let [Some(limit), Some(reservation)] = cpus {
+            if reservation > limit {
+                let span = resources.get("cpu_reservation").and_then(Item::span);
+                self.report(
+                    span,
+                    format!("cpu_reservation in {} exceeds cpu_limit", context),
+                );
+            }
+        }
+
+        let mut memory = [None, None];
+        for (slot, key) in ["memory_limit", "memory_reservation"].iter().enumerate() {
+            let Some(item) = resources.get(key) else {
+                continue;
+            };
+            let Some(raw) = item.as_str() else {
+                continue;
+            };
+            match parse_memory_to_bytes(raw) {
+                Ok(bytes) => memory[slot] = Some(bytes),
+                Err(AetherError::Config(e)) => {
+                    self.report(item.span(), format!("{} in {}: {}", key, context, e))
+                }
+                Err(e) => self.report(item.span(), format!("{} in {}: {}", key, context, e)),
+            }
+        }
+        if let [Some(limit), Some(reservation)] = memory {
+            if reservation > limit {
+                let span = resources.get("memory_reservation").and_then(Item::span);
+                self.report(
+                    span,
+                    format!("memory_reservation in {} exceeds memory_limit", context),
+                );
+            }
+        }
+    }
+
+    /// `${VAR}` references in the strings of `item` must expand.
+    fn check_interpolation(&mut self, item: &Item) {
+        let values: Vec<&toml_edit::Value> = match item {
+            Item::Value(toml_edit::Value::Array(array)) => array.iter().collect(),
+            Item::Value(toml_edit::Value::InlineTable(table)) => {
+                table.iter().map(|(_, value)| value).collect()
+            }
+            Item::Value(value) => vec![value],
+            Item::Table(table) => table.iter().filter_map(|(_, i)| i.as_value()).collect(),
+            _ => Vec::new(),
+        };
+
+        for value in values {
+            let Some(text) = value.as_str() else {
+                continue;
+            };
+            if let Err(e) = interpolate(text, &env_lookup) {
+                let span = value.span().unwrap_or(0..0);
+                // Point at the reference itself when it appears verbatim in the source
+                let reference = &text[e.offset..];
+                let reference =
+                    &reference[..reference.find('}').map_or(reference.len(), |i| i + 1)];
+                let offset = self.source[span.clone()]
+                    .find(reference)
+                    .map_or(span.start, |i| span.start + i);
+                self.report(Some(offset..offset), e.message);
+            }
+        }
+    }
+
+    /// `extends` must name a template or another service, without cycles.
+    fn check_extends(&mut self, templates: Option<&toml_edit::Table>, services: &toml_edit::Table) {
+        for (name, service) in services.iter() {
+            let Some(extends) = service.as_table_like().and_then(|s| s.get("extends")) else {
+                continue;
+            };
+            let Some(base) = extends.as_str() else {
+                continue;
+            };
+
+            let own = vec!["services".to_string(), name.to_string()];
+            let mut chain = vec![name.to_string()];
+            let mut at = base_path(templates, Some(services), base);
+            let mut problem = None;
+            while let Some(path) = at {
+                chain.push(path[1].clone());
+                if path == own {
+                    problem = Some(if chain.len() == 2 {
+                        format!("service `{}` extends itself", name)
+                    } else {
+                        format!("extends cycle: {}", chain.join(" -> "))
+                    });
+                    break;
+                }
+                if chain.len() > services.len() + templates.map_or(0, |t| t.len()) + 1 {
+                    // A cycle not through this service; reported from its members
+                    break;
+                }
+                let next = templates
+                    .filter(|_| path[0] == "templates")
+                    .unwrap_or(services)
+                    .get(&path[1])
+                    .and_then(Item::as_table_like)
+                    .and_then(|t| t.get("extends"))
+                    .and_then(Item::as_str);
+                at = next.and_then(|next| base_path(templates, Some(services), next));
+            }
+
+            if base_path(templates, Some(services), base).is_none() {
+                problem = Some(format!(
+                    "service `{}` extends unknown template or service `{}`",
+                    name, base
+                ));
+            }
+            if let Some(problem) = problem {
+                self.report(extends.span(), problem);
+            }
+        }
+    }
+
+    /// `depends_on` entries must name another service, without cycles.
+    fn check_dependencies(&mut self, services: &dyn TableLike) {
+        let mut graph: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+        for (name, service) in services.iter() {
+            let Some(depends_on) = service
+                .as_table_like()
+                .and_then(|s| s.get("depends_on"))
+                .and_then(Item::as_array)
+            else {
+                continue;
+            };
+            for dependency in depends_on.iter() {
+                let Some(target) = dependency.as_str() else {
+                    continue;
+                };
+                if target == name {
+                    self.report(
+                        dependency.span(),
+                        format!("service `{}` depends on itself", name),
+                    );
+                } else if services.get(target).is_none() {
+                    self.report(
+                        dependency.span(),
+                        format!("service `{}` depends on unknown service `{}`", name, target),
+                    );
+                } else {
+                    graph.entry(name).or_default().push(target);
+                }
+            }
+        }
+
+        let mut reported = HashSet::new();
+        for start in graph.keys() {
+            if let Some(cycle) = find_cycle(&graph, start) {
+                let mut members = cycle[1..].to_vec();
+                members.sort();
+                if reported.insert(members) {
+                    let span = services
+                        .get(start)
+                        .and_then(Item::as_table_like)
+                        .and_then(|s| s.get_key_value("depends_on"))
+                        .and_then(|(k, _)| k.span());
+                    self.report(span, format!("dependency cycle: {}", cycle.join(" -> ")));
+                }
+            }
+        }
+    }
+
+    /// Every `services.*` variable the injection template uses must exist
+    /// in the context rendered from the configured services.
+    fn check_template(&mut self, template: &Item, declared: &BTreeMap<String, Declared>) {
+        let Some(text) = template.as_str() else {
+            return;
+        };
+        let span = template.span().unwrap_or(0..0);
+        let raw = &self.source[span.clone()];
+        let mut search_from = 0;
+
+        for (expression, variables) in template_variables(text) {
+            // Locate the expression in the raw TOML string to report its position
+            let found = raw[search_from..]
+                .find(&expression)
+                .map(|i| i + search_from);
+            if let Some(at) = found {
+                search_from = at + expression.len();
+            }
+
+            for variable in variables {
+                if let Some(problem) = check_variable(&variable, declared) {
+                    let offset = found
+                        .map(|at| span.start + at + expression.find(&variable).unwrap_or(0))
+                        .unwrap_or(span.start);
+                    self.report(Some(offset..offset), problem);
+                }
+            }
+        }
+    }
+}
+
+/// A dependency cycle through `start`, as the path back to it.
+fn find_cycle<'a>(graph: &BTreeMap<&'a str, Vec<&'a str>>, start: &'a str) -> Option<Vec<&'a str>> {
+    fn visit<'a>(
+        graph: &BTreeMap<&'a str, Vec<&'a str>>,
+        start: &'a str,
+        path: &mut Vec<&'a str>,
+    ) -> bool {
+        let current = *path.last().expect("path starts non-empty");
+        for &next in graph.get(current).into_iter().flatten() {
+            if next == start {
+                path.push(next);
+                return true;
+            }
+            if !path.contains(&next) {
+                path.push(next);
+                if visit(graph, start, path) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+
+    let mut path = vec![start];
+    visit(graph, start, &mut path).then_some(path)
+}
+
+/// Handlebars expressions in `template` with the `services` variables each
+/// one references. Helper names, literals and hash arguments are skipped.
+fn template_variables(template: &str) -> Vec<(String, Vec<String>)> {
+    let mut expressions = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let expression = &rest[start..start + len + 2];
+        rest = &rest[start + len + 2..];
+
+        let inner = expression
+            .trim_start_matches('{')
+            .trim_end_matches('}')
+            .trim_matches('~')
+            .trim();
+        if inner.starts_with('!') {
+            continue;
+        }
+        let inner = inner.trim_start_matches(['#', '/', '^', '&', '>']);
+
+        let variables = inner
+            .split_whitespace()
+            .map(|token| token.trim_matches(['(', ')']))
+            .map(|token| token.strip_prefix("@root.").unwrap_or(token))
+            .filter(|token| *token == "services" || token.starts_with("services."))
+            .map(str::to_string)
+            .collect();
+        expressions.push((expression.to_string(), variables));
+    }
+
+    expressions
+}
+
+/// Why `variable` doesn't resolve against the configured services, if it
+/// doesn't.
+fn check_variable(variable: &str, declared: &BTreeMap<String, Declared>) -> Option<String> {
+    let segments: Vec<&str> = variable
+        .split('.')
+        .map(|s| s.trim_start_matches('[').trim_end_matches(']'))
+        .collect();
+
+    match segments.as_slice() {
+        ["services"] => None,
+        ["services", service, rest @ ..] => {
+            let Some(declared) = declared.get(*service) else {
+                return Some(format!(
+                    "template variable `{}` refers to unknown service `{}`",
+                    variable, service
+                ));
+            };
+            match rest {
+                [] | ["ports"] | ["container_id"] => None,
+                ["ports", port] if declared.ports.iter().any(|p| p == port) => None,
+                ["ports", port] => Some(format!(
+                    "template variable `{}` refers to port {} which service `{}` does not declare",
+                    variable, port, service
+                )),
+                [value] if declared.values.contains(value) => None,
+                _ => Some(format!("unknown template variable `{}`", variable)),
+            }
+        }
+        _ => Some(format!("unknown template variable `{}`", variable)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str) -> Vec<String> {
+        validate_source(source, "aether.toml")
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let source = r#"
+[backend]
+type = "docker"
+
+[services.postgres]
+image = "postgres:15"
+ports = ["5432"]
+resources = { cpu_limit = 2, memory_limit = "1g", memory_reservation = "512mb" }
+
+[services.app]
+image = "app:latest"
+depends_on = ["postgres"]
+
+[injection]
+file = ".env"
+template = "DB={{ services.postgres.ports.5432 }} ID={{services.app.container_id}}"
+"#;
+        assert_eq!(messages(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_syntax_error_position() {
+        let diagnostics = validate_source("[backend]\ntype = \n", "aether.toml");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 8));
+    }
+
+    #[test]
+    fn test_reports_every_problem_with_position() {
+        let source = r#"[backend]
+type = "docker"
+sockt = "/var/run/docker.sock"
+
+[services.postgres]
+image = "postgres:15"
+ports = ["5432", "99999", "5432"]
+depends_on = ["cache", "postgres"]
+
+[services.postgres.resources]
+memory_limit = "lots"
+cpu_limit = 0
+
+[injection]
+file = ".env"
+template = "DB={{ services.postgres.ports.6543 }} R={{ services.redis.ports.6379 }}"
+"#;
+        assert_eq!(
+            messages(source),
+            vec![
+                "aether.toml:3:1: unknown key `sockt` in [backend]",
+                "aether.toml:7:18: invalid port `99999` in [services.postgres]: expected a number from 1 to 65535",
+                "aether.toml:7:27: port 5432 is declared twice in [services.postgres]",
+                "aether.toml:8:15: service `postgres` depends on unknown service `cache`",
+                "aether.toml:8:24: service `postgres` depends on itself",
+                "aether.toml:11:16: memory_limit in [services.postgres.resources]: Invalid memory value: lots",
+                "aether.toml:12:13: cpu_limit in [services.postgres.resources] must be greater than 0",
+                "aether.toml:16:19: template variable `services.postgres.ports.6543` refers to port 6543 which service `postgres` does not declare",
+                "aether.toml:16:56: template variable `services.redis.ports.6379` refers to unknown service `redis`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unset_variable_position() {
+        let source = r#"[backend]
+type = "docker"
+
+[services.postgres]
+image = "postgres:${AETHER_TEST_PG_VERSION:-15}"
+env = { POSTGRES_PASSWORD = "${AETHER_TEST_UNSET_PASSWORD}" }
+"#;
+        let found = messages(source);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].starts_with(
+            "aether.toml:6:30: environment variable `AETHER_TEST_UNSET_PASSWORD` is not set"
+        ));
+    }
+
+    #[test]
+    fn test_dependency_cycle() {
+        let source = r#"
+[backend]
+type = "docker"
+
+[services.a]
+image = "a"
+depends_on = ["b"]
+
+[services.b]
+image = "b"
+depends_on = ["a"]
+"#;
+        let found = messages(source);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].ends_with("dependency cycle: a -> b -> a"));
+    }
+
+    #[test]
+    fn test_extends() {
+        let source = r#"[backend]
+type = "docker"
+
+[templates.base-postgres]
+image = "postgres:15"
+ports = ["54321x"]
+
+[services.pg]
+extends = "base-postgres"
+
+[services.a]
+image = "a"
+extends = "b"
+
+[services.b]
+image = "b"
+extends = "a"
+
+[services.c]
+image = "c"
+extends = "c"
+
+[services.d]
+extends = "base-mysql"
+image = "mysql"
+"#;
+        assert_eq!(
+            messages(source),
+            vec![
+                "aether.toml:6:10: invalid port `54321x` in [services.pg]: expected a number from 1 to 65535",
+                "aether.toml:13:11: extends cycle: a -> b -> a",
+                "aether.toml:17:11: extends cycle: b -> a -> b",
+                "aether.toml:21:11: service `c` extends itself",
+                "aether.toml:24:11: service `d` extends unknown template or service `base-mysql`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        let source = r#"[backend]
+type = "docker"
+
+[services.db]
+preset = "postgres"
+env = { POSTGRES_DB = "app" }
+healthcheck = { interval = "soon" }
+
+[services.queue]
+preset = "activemq"
+image = "activemq"
+
+[injection]
+file = ".env"
+template = "DATABASE_URL={{ services.db.url }} USER={{ services.db.user }} {{ services.db.bucket }}"
+"#;
+        assert_eq!(
+            messages(source),
+            vec![
+                "aether.toml:7:28: interval in [services.db.healthcheck]: Invalid duration: soon",
+                "aether.toml:10:10: unknown preset `activemq` in [services.queue] (expected one of: postgres, mysql, redis, mongodb, rabbitmq, kafka, minio, mailpit, elasticsearch)",
+                "aether.toml:15:79: unknown template variable `services.db.bucket`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_container_options() {
+        let source = r#"[backend]
+type = "docker"
+
+[services.search]
+image = "elasticsearch:8.13.0"
+shm_size = "lots"
+restart = "sometimes"
+stop_grace_period = "30s"
+ulimits = { memlock = -1, nofile = { soft = 65536, hard = 1024 } }
+labels = { "aether.service" = "x", team = "search" }
+"#;
+        assert_eq!(
+            messages(source),
+            vec![
+                "aether.toml:6:12: shm_size in [services.search]: Invalid memory value: lots",
+                "aether.toml:7:11: restart in [services.search]: Invalid restart policy: sometimes (expected no, always, unless-stopped, on-failure or on-failure:<retries>)",
+                "aether.toml:9:45: soft limit exceeds hard limit in ulimit `nofile` of [services.search]",
+                "aether.toml:10:12: label `aether.service` in [services.search] is reserved for Aether",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_known_keys_match_json_schema() {
+        let schema = crate::config::config_schema();
+        let keys = |schema: &serde_json::Value| {
+            let mut keys: Vec<String> = schema["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            keys.sort();
+            keys
+        };
+        let sorted = |known: &[&str]| {
+            let mut known: Vec<String> = known.iter().map(|k| k.to_string()).collect();
+            known.sort();
+            known
+        };
+        let defs = &schema["$defs"];
+
+        assert_eq!(keys(&schema), sorted(TOP_LEVEL_KEYS));
+        assert_eq!(
+            keys(&defs["BackendConfig"]["oneOf"][0]),
+            sorted(BACKEND_KEYS)
+        );
+        assert_eq!(keys(&defs["ServiceConfig"]), sorted(SERVICE_KEYS));
+        assert_eq!(keys(&defs["ResourceLimits"]), sorted(RESOURCE_KEYS));
+        assert_eq!(keys(&defs["HealthcheckConfig"]), sorted(HEALTHCHECK_KEYS));
+        assert_eq!(keys(&defs["Ulimit"]["anyOf"][1]), sorted(ULIMIT_KEYS));
+        assert_eq!(keys(&defs["InjectionConfig"]), sorted(INJECTION_KEYS));
+    }
+
+    #[test]
+    fn test_type_errors_come_from_deserialization() {
+        let found = messages("[backend]\ntype = \"podman\"\n");
+        assert_eq!(found.len(), 1);
+        assert!(found[0].starts_with("aether.toml:2:8: unknown variant `podman`"));
+    }
+
+    #[test]
+    fn test_template_variables_skip_helpers_and_literals() {
+        let variables = template_variables(
+            "{{#if services.redis}}{{ default services.redis.ports.[6379] \"6379\" }}{{/if}}{{! services.none }}",
+        );
+        let found: Vec<String> = variables.into_iter().flat_map(|(_, v)| v).collect();
+        assert_eq!(found, vec!["services.redis", "services.redis.ports.[6379]"]);
+
+        let mut declared = BTreeMap::new();
+        declared.insert(
+            "redis".to_string(),
+            Declared {
+                ports: vec!["6379".to_string()],
+                values: Vec::new(),
+            },
+        );
+        assert_eq!(
+            check_variable("services.redis.ports.[6379]", &declared),
+            None
+        );
+        assert!(check_variable("services.redis.host", &declared).is_some());
+        declared.get_mut("redis").unwrap().values = vec!["host", "url"];
+        assert_eq!(check_variable("services.redis.url", &declared), None);
+    }
+}
@@ -8,6 +8,9 @@ pub enum AetherError {
     #[error("Jujutsu command failed: {message} (exit code: {exit_code})")]
     Jj { message: String, exit_code: i32 },
 
+    #[error("Git command failed: {message} (exit code: {exit_code})")]
+    Git { message: String, exit_code: i32 },
+
     #[error("Backend error: {0}")]
     Backend(String),
 
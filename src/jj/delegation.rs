@@ -1,8 +1,10 @@
 use crate::error::{AetherError, Result};
-use std::process::{Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
 
 pub struct JjCommand {
     args: Vec<String>,
+    cwd: Option<PathBuf>,
 }
 
 pub struct JjOutput {
@@ -12,7 +14,13 @@ pub struct JjOutput {
 
 impl JjCommand {
     pub fn new(args: Vec<String>) -> Self {
-        Self { args }
+        Self { args, cwd: None }
+    }
+
+    /// Run jj from `dir` instead of the current directory.
+    pub fn in_dir(mut self, dir: &Path) -> Self {
+        self.cwd = Some(dir.to_path_buf());
+        self
     }
 
     pub fn workspace_add(destination: &str, revision: Option<&str>) -> Self {
@@ -27,44 +35,98 @@ impl JjCommand {
             args.push(rev.to_string());
         }
 
-        Self { args }
+        Self::new(args)
     }
 
     pub fn workspace_forget(workspace: &str) -> Self {
-        Self {
-            args: vec![
-                "workspace".to_string(),
-                "forget".to_string(),
-                workspace.to_string(),
-            ],
-        }
+        Self::new(vec![
+            "workspace".to_string(),
+            "forget".to_string(),
+            workspace.to_string(),
+        ])
+    }
+
+    pub fn workspace_list() -> Self {
+        Self::new(vec!["workspace".to_string(), "list".to_string()])
+    }
+
+    pub fn log(revset: &str) -> Self {
+        Self::new(vec![
+            "log".to_string(),
+            "--no-graph".to_string(),
+            "-r".to_string(),
+            revset.to_string(),
+        ])
+    }
+
+    /// Render output with a jj template instead of the default format.
+    pub fn with_template(mut self, template: &str) -> Self {
+        self.args.push("-T".to_string());
+        self.args.push(template.to_string());
+        self
+    }
+
+    pub fn op_log(limit: usize) -> Self {
+        Self::new(vec![
+            "op".to_string(),
+            "log".to_string(),
+            "--no-graph".to_string(),
+            "--limit".to_string(),
+            limit.to_string(),
+        ])
+    }
+
+    /// Print `path` as of `revision`.
+    pub fn file_show(revision: &str, path: &str) -> Self {
+        Self::new(vec![
+            "file".to_string(),
+            "show".to_string(),
+            "-r".to_string(),
+            revision.to_string(),
+            path.to_string(),
+        ])
+    }
+
+    /// List the tracked files matching `path`.
+    pub fn file_list(path: &str) -> Self {
+        Self::new(vec![
+            "file".to_string(),
+            "list".to_string(),
+            path.to_string(),
+        ])
+    }
+
+    pub fn undo() -> Self {
+        Self::new(vec!["undo".to_string()])
+    }
+
+    /// Don't snapshot the working copy first, so the command records no
+    /// operation of its own.
+    pub fn ignore_working_copy(mut self) -> Self {
+        self.args.push("--ignore-working-copy".to_string());
+        self
     }
 
     pub fn status() -> Self {
-        Self {
-            args: vec!["status".to_string()],
+        Self::new(vec!["status".to_string()])
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new("jj");
+        if let Some(dir) = &self.cwd {
+            command.current_dir(dir);
         }
+        command.args(&self.args);
+        command
     }
 
     pub fn execute(&self) -> Result<JjOutput> {
-        let output = Command::new("jj")
-            .args(&self.args)
+        let output = self
+            .command()
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .output()
-            .map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    AetherError::Jj {
-                        message: "jj command not found. Please install Jujutsu.".to_string(),
-                        exit_code: -1,
-                    }
-                } else {
-                    AetherError::Jj {
-                        message: format!("Failed to execute jj: {}", e),
-                        exit_code: -1,
-                    }
-                }
-            })?;
+            .map_err(spawn_error)?;
 
         if !output.status.success() {
             return Err(AetherError::Jj {
@@ -78,6 +140,100 @@ impl JjCommand {
             stderr: String::from_utf8_lossy(&output.stderr).to_string(),
         })
     }
+
+    /// Run jj attached to the terminal, so colors, pagers and editors work,
+    /// and return the exit code it finished with. A failing jj is not an
+    /// error here; only failing to start it is.
+    ///
+    /// Like a shell waiting for its foreground job, ajj ignores Ctrl-C and
+    /// Ctrl-\ meanwhile: they reach jj, and ajj reports how jj exited.
+    pub fn run_inherited(&self) -> Result<i32> {
+        let mut command = self.command();
+        let interrupts = IgnoreInterrupts::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            // Only undo ajj's own SIG_IGN: a signal the caller already
+            // ignored (nohup, background jobs) stays ignored in jj too.
+            let previous = interrupts.previous;
+            // SAFETY: signal() is async-signal-safe
+            unsafe {
+                command.pre_exec(move || {
+                    for (signal, handler) in previous {
+                        if handler != libc::SIG_IGN {
+                            libc::signal(signal, libc::SIG_DFL);
+                        }
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        let status = command.status().map_err(spawn_error)?;
+        drop(interrupts);
+        Ok(exit_code(status))
+    }
+}
+
+/// Ignores SIGINT and SIGQUIT until dropped, then restores the previous
+/// dispositions.
+struct IgnoreInterrupts {
+    #[cfg(unix)]
+    previous: [(libc::c_int, libc::sighandler_t); 2],
+}
+
+impl IgnoreInterrupts {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            // SAFETY: SIG_IGN installs no handler code
+            let ignore = |signal| (signal, unsafe { libc::signal(signal, libc::SIG_IGN) });
+            Self {
+                previous: [ignore(libc::SIGINT), ignore(libc::SIGQUIT)],
+            }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+}
+
+impl Drop for IgnoreInterrupts {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        for (signal, handler) in self.previous {
+            // SAFETY: restores a disposition signal() returned earlier
+            unsafe {
+                libc::signal(signal, handler);
+            }
+        }
+    }
+}
+
+fn spawn_error(e: std::io::Error) -> AetherError {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        AetherError::Jj {
+            message: "jj command not found. Please install Jujutsu.".to_string(),
+            exit_code: -1,
+        }
+    } else {
+        AetherError::Jj {
+            message: format!("Failed to execute jj: {}", e),
+            exit_code: -1,
+        }
+    }
+}
+
+/// Exit code to report for `status`, using the shell's `128 + signal`
+/// convention when jj was killed by a signal.
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
 }
 
 #[cfg(test)]
@@ -105,9 +261,62 @@ mod tests {
         assert_eq!(cmd.args, vec!["workspace", "forget", "test-ws"]);
     }
 
+    #[test]
+    fn test_workspace_list_args() {
+        let cmd = JjCommand::workspace_list();
+        assert_eq!(cmd.args, vec!["workspace", "list"]);
+    }
+
+    #[test]
+    fn test_log_with_template_args() {
+        let cmd = JjCommand::log("@").with_template("change_id");
+        assert_eq!(
+            cmd.args,
+            vec!["log", "--no-graph", "-r", "@", "-T", "change_id"]
+        );
+    }
+
+    #[test]
+    fn test_op_log_args() {
+        let cmd = JjCommand::op_log(1).ignore_working_copy();
+        assert_eq!(
+            cmd.args,
+            vec![
+                "op",
+                "log",
+                "--no-graph",
+                "--limit",
+                "1",
+                "--ignore-working-copy"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_show_args() {
+        let cmd = JjCommand::file_show("main", "aether.toml");
+        assert_eq!(cmd.args, vec!["file", "show", "-r", "main", "aether.toml"]);
+    }
+
+    #[test]
+    fn test_file_list_args() {
+        let cmd = JjCommand::file_list("aether.local.toml");
+        assert_eq!(cmd.args, vec!["file", "list", "aether.local.toml"]);
+    }
+
     #[test]
     fn test_status_args() {
         let cmd = JjCommand::status();
         assert_eq!(cmd.args, vec!["status"]);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_code() {
+        use std::os::unix::process::ExitStatusExt;
+        // Raw wait statuses: exit code in the high byte, signal in the low bits
+        assert_eq!(exit_code(ExitStatus::from_raw(0)), 0);
+        assert_eq!(exit_code(ExitStatus::from_raw(3 << 8)), 3);
+        assert_eq!(exit_code(ExitStatus::from_raw(2)), 130);
+    }
 }
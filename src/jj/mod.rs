@@ -1,5 +1,6 @@
 pub mod delegation;
 pub mod parser;
+pub mod query;
 
 pub use delegation::*;
 pub use parser::*;
@@ -1,4 +1,57 @@
-use crate::error::Result;
+use crate::error::{AetherError, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// A commit as rendered by [`crate::jj::query`]'s commit template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JjCommit {
+    pub change_id: String,
+    pub commit_id: String,
+    /// First line of the description
+    pub description: String,
+    pub bookmarks: Vec<String>,
+    pub conflict: bool,
+    pub empty: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JjWorkspace {
+    pub name: String,
+    /// Working-copy commit of the workspace
+    pub target: JjCommit,
+}
+
+/// An operation as rendered by [`crate::jj::query`]'s operation template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JjOperation {
+    pub id: String,
+    pub description: String,
+    pub time: String,
+    pub user: String,
+}
+
+/// One line of `jj diff --summary`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileChange {
+    /// `M`, `A`, `D`, `R` or `C`
+    pub status: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffStatFile {
+    pub path: String,
+    /// Lines added plus lines removed
+    pub changes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiffStat {
+    pub files: Vec<DiffStatFile>,
+    pub files_changed: u64,
+    pub insertions: u64,
+    pub deletions: u64,
+}
 
 pub struct JjStatus {
     pub working_copy: Option<String>,
@@ -15,10 +68,102 @@ pub fn parse_status(output: &str) -> Result<JjStatus> {
     Ok(JjStatus { working_copy })
 }
 
+/// Parse template output that renders one JSON object per line.
+pub fn parse_json_lines<T: DeserializeOwned>(output: &str) -> Result<Vec<T>> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| AetherError::Jj {
+                message: format!("Unexpected jj template output ({}): {}", e, line),
+                exit_code: -1,
+            })
+        })
+        .collect()
+}
+
+/// Parse `jj diff --summary`, one `<status> <path>` entry per line.
+pub fn parse_diff_summary(output: &str) -> Vec<FileChange> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(status, path)| FileChange {
+            status: status.to_string(),
+            path: path.trim().to_string(),
+        })
+        .collect()
+}
+
+/// Parse `jj diff --stat`: a `<path> | <changes> <bar>` line per file and a
+/// trailing `N files changed, N insertions(+), N deletions(-)` summary.
+pub fn parse_diff_stat(output: &str) -> DiffStat {
+    let mut stat = DiffStat::default();
+
+    for line in output.lines() {
+        if let Some((path, rest)) = line.rsplit_once(" | ") {
+            let changes = rest
+                .split_whitespace()
+                .next()
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(0);
+            stat.files.push(DiffStatFile {
+                path: path.trim().to_string(),
+                changes,
+            });
+        } else if line.contains("changed") {
+            for part in line.split(',') {
+                let mut words = part.split_whitespace();
+                let (Some(count), Some(label)) = (words.next(), words.next()) else {
+                    continue;
+                };
+                let Ok(count) = count.parse() else {
+                    continue;
+                };
+                if label.starts_with("file") {
+                    stat.files_changed = count;
+                } else if label.starts_with("insertion") {
+                    stat.insertions = count;
+                } else if label.starts_with("deletion") {
+                    stat.deletions = count;
+                }
+            }
+        }
+    }
+
+    stat
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_commit() {
+        let output = r#"{"change_id":"qpvuntsmwlqt","commit_id":"230dd059e1b0","description":"fix: login bug","bookmarks":["main","feature-x"],"conflict":false,"empty":true}
+"#;
+        let commits: Vec<JjCommit> = parse_json_lines(output).unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].change_id, "qpvuntsmwlqt");
+        assert_eq!(commits[0].bookmarks, vec!["main", "feature-x"]);
+        assert!(commits[0].empty);
+        assert!(!commits[0].conflict);
+    }
+
+    #[test]
+    fn test_parse_workspace_template_lines() {
+        let output = concat!(
+            r#"{"name":"default","target":{"change_id":"qpvuntsm","commit_id":"230dd059","description":"","bookmarks":[],"conflict":false,"empty":true}}"#,
+            "\n",
+            r#"{"name":"feature-x","target":{"change_id":"kkmpptxz","commit_id":"3d4e5f60","description":"fix: login bug","bookmarks":[],"conflict":true,"empty":false}}"#,
+            "\n",
+        );
+        let workspaces: Vec<JjWorkspace> = parse_json_lines(output).unwrap();
+        assert_eq!(workspaces.len(), 2);
+        assert_eq!(workspaces[0].name, "default");
+        assert_eq!(workspaces[1].name, "feature-x");
+        assert!(workspaces[1].target.conflict);
+    }
+
     #[test]
     fn test_parse_status_basic() {
         let output = "Working copy: main @ abc123\nParent commit: xyz789";
@@ -32,4 +177,33 @@ mod tests {
         let status = parse_status(output).unwrap();
         assert!(status.working_copy.is_none());
     }
+
+    #[test]
+    fn test_parse_rejects_text_output() {
+        let result: Result<Vec<JjWorkspace>> =
+            parse_json_lines("default: qpvuntsm 230dd059 (empty) (no description set)\n");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_diff_summary() {
+        let changes = parse_diff_summary("M src/main.rs\nA docs/new file.md\n");
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].status, "M");
+        assert_eq!(changes[1].path, "docs/new file.md");
+    }
+
+    #[test]
+    fn test_parse_diff_stat() {
+        let output = "src/main.rs | 10 +++++-----\n\
+                      README.md   |  2 +-\n\
+                      2 files changed, 6 insertions(+), 6 deletions(-)\n";
+        let stat = parse_diff_stat(output);
+        assert_eq!(stat.files.len(), 2);
+        assert_eq!(stat.files[0].path, "src/main.rs");
+        assert_eq!(stat.files[0].changes, 10);
+        assert_eq!(stat.files_changed, 2);
+        assert_eq!(stat.insertions, 6);
+        assert_eq!(stat.deletions, 6);
+    }
 }
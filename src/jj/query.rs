@@ -0,0 +1,140 @@
+//! Typed jj queries. Each one renders its result as JSON through a `-T`
+//! template, so nothing depends on jj's human-readable output.
+
+use super::delegation::JjCommand;
+use super::parser::{parse_json_lines, JjCommit, JjOperation, JjWorkspace};
+use crate::error::{AetherError, Result};
+use std::path::Path;
+
+/// Template rendering a commit as a JSON object. `receiver` is the prefix
+/// for the commit's methods: empty in `jj log`, where they are bare
+/// keywords, `target.` in `workspace list`, where they are method calls.
+fn commit_template(receiver: &str) -> String {
+    let call = if receiver.is_empty() { "" } else { "()" };
+    format!(
+        concat!(
+            r#""{{" "#,
+            r#"++ "\"change_id\":" ++ stringify({r}change_id{c}).escape_json() "#,
+            r#"++ ",\"commit_id\":" ++ stringify({r}commit_id{c}).escape_json() "#,
+            r#"++ ",\"description\":" ++ {r}description{c}.first_line().escape_json() "#,
+            r#"++ ",\"bookmarks\":[" ++ {r}bookmarks{c}.map(|b| stringify(b.name()).escape_json()).join(",") ++ "]" "#,
+            r#"++ ",\"conflict\":" ++ if({r}conflict{c}, "true", "false") "#,
+            r#"++ ",\"empty\":" ++ if({r}empty{c}, "true", "false") "#,
+            r#"++ "}}""#,
+        ),
+        r = receiver,
+        c = call
+    )
+}
+
+/// `jj log` template printing one JSON commit per line.
+pub fn commit_lines_template() -> String {
+    format!(r#"{} ++ "\n""#, commit_template(""))
+}
+
+/// `jj op log` template printing one JSON operation per line.
+pub fn operation_lines_template() -> String {
+    concat!(
+        r#""{" "#,
+        r#"++ "\"id\":" ++ stringify(id).escape_json() "#,
+        r#"++ ",\"description\":" ++ description.escape_json() "#,
+        r#"++ ",\"time\":" ++ time.start().format("%Y-%m-%dT%H:%M:%S%:z").escape_json() "#,
+        r#"++ ",\"user\":" ++ user.escape_json() "#,
+        r#"++ "}\n""#,
+    )
+    .to_string()
+}
+
+/// `jj workspace list` template printing one JSON workspace per line.
+pub fn workspace_template() -> String {
+    format!(
+        r#""{{\"name\":" ++ stringify(name).escape_json() ++ ",\"target\":" ++ {} ++ "}}\n""#,
+        commit_template("target.")
+    )
+}
+
+/// Commits in `revset`, evaluated in the workspace at `dir`.
+pub fn commits(dir: &Path, revset: &str) -> Result<Vec<JjCommit>> {
+    let output = JjCommand::log(revset)
+        .with_template(&commit_lines_template())
+        .in_dir(dir)
+        .execute()?;
+    parse_json_lines(&output.stdout)
+}
+
+fn single<T>(items: Vec<T>, what: &str) -> Result<T> {
+    items.into_iter().next().ok_or_else(|| AetherError::Jj {
+        message: format!("jj returned no {}", what),
+        exit_code: -1,
+    })
+}
+
+/// The working-copy commit of the workspace at `dir`.
+pub fn working_copy(dir: &Path) -> Result<JjCommit> {
+    single(commits(dir, "@")?, "working-copy commit")
+}
+
+/// [`working_copy`] as of jj's last snapshot. Files ajj just wrote into the
+/// workspace are not snapshotted, so no operation is recorded.
+pub fn last_snapshot(dir: &Path) -> Result<JjCommit> {
+    let output = JjCommand::log("@")
+        .with_template(&commit_lines_template())
+        .ignore_working_copy()
+        .in_dir(dir)
+        .execute()?;
+    single(parse_json_lines(&output.stdout)?, "working-copy commit")
+}
+
+/// The most recent operation of the repository containing `dir`, without
+/// snapshotting the working copy: right after a mutating jj command this is
+/// that command's operation, not a snapshot of files changed since.
+pub fn current_operation(dir: &Path) -> Result<JjOperation> {
+    let output = JjCommand::op_log(1)
+        .with_template(&operation_lines_template())
+        .ignore_working_copy()
+        .in_dir(dir)
+        .execute()?;
+    single(parse_json_lines(&output.stdout)?, "operation")
+}
+
+/// The operation `jj undo` in `dir` would undo: [`current_operation`] after
+/// snapshotting the working copy the way `jj undo` itself does.
+pub fn undo_target(dir: &Path) -> Result<JjOperation> {
+    let output = JjCommand::op_log(1)
+        .with_template(&operation_lines_template())
+        .in_dir(dir)
+        .execute()?;
+    single(parse_json_lines(&output.stdout)?, "operation")
+}
+
+/// Every workspace of the repository containing `dir`.
+pub fn workspaces(dir: &Path) -> Result<Vec<JjWorkspace>> {
+    let output = JjCommand::workspace_list()
+        .with_template(&workspace_template())
+        .in_dir(dir)
+        .execute()?;
+    parse_json_lines(&output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_template_receiver() {
+        let template = commit_template("target.");
+        assert!(template.contains("stringify(target.change_id()).escape_json()"));
+        assert!(template.contains("target.description().first_line()"));
+        assert!(template.contains("target.bookmarks().map("));
+        assert!(template.contains("if(target.conflict(), \"true\", \"false\")"));
+        assert!(template.contains("if(target.empty(), \"true\", \"false\")"));
+        let bare = commit_template("");
+        assert!(bare.contains("stringify(change_id).escape_json()"));
+        assert!(bare.contains("if(empty, \"true\", \"false\")"));
+        assert!(template.starts_with("\"{\""));
+        assert!(template.ends_with("\"}\""));
+        assert!(workspace_template().contains("stringify(name)"));
+        assert!(commit_lines_template().ends_with(r#"++ "\n""#));
+        assert!(operation_lines_template().contains("stringify(id)"));
+    }
+}
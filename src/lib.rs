@@ -6,5 +6,6 @@ pub mod jj;
 pub mod output;
 pub mod provisioner;
 pub mod repo;
+pub mod vcs;
 
 pub use error::*;
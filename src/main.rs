@@ -1,9 +1,12 @@
 use aether::cli::{
-    generate_completion, handle_cleanup, handle_container_run, handle_list, handle_logs,
-    handle_restart, handle_run, handle_start, handle_status, handle_stop, handle_workspace_add,
-    handle_workspace_forget,
+    generate_completion, handle_cleanup, handle_config_schema, handle_config_show,
+    handle_config_validate, handle_container_run, handle_export_compose, handle_gc, handle_init,
+    handle_jj_json, handle_list, handle_logs, handle_restart, handle_run, handle_start,
+    handle_state_migrate, handle_state_repair, handle_status, handle_stop, handle_sync,
+    handle_undo, handle_workspace_add, handle_workspace_attach, handle_workspace_detach,
+    handle_workspace_forget, workspace_path,
 };
-use aether::cli::{Cli, Commands, WorkspaceAction};
+use aether::cli::{Cli, Commands, ConfigAction, ExportAction, StateAction, WorkspaceAction};
 use aether::jj::JjCommand;
 use clap::Parser;
 use std::path::Path;
@@ -13,6 +16,10 @@ async fn main() {
     let cli = Cli::parse();
     let json = cli.is_json();
     let config_path = cli.config.clone();
+    let profile = cli.profile.clone();
+    let profile = profile.as_deref();
+    let workspace = cli.workspace.clone();
+    let workspace = workspace.as_deref();
 
     let result = match cli.command {
         Commands::Workspace { action } => match action {
@@ -24,15 +31,29 @@ async fn main() {
                     &destination,
                     revision.as_deref(),
                     config_path.as_deref(),
+                    profile,
                     json,
                 )
                 .await
             }
-            WorkspaceAction::Forget { workspace } => {
-                handle_workspace_forget(&workspace, json).await
+            WorkspaceAction::Forget { workspace, force } => {
+                handle_workspace_forget(&workspace, force, json).await
+            }
+            WorkspaceAction::Attach { path, name } => {
+                handle_workspace_attach(
+                    path.as_deref(),
+                    name.as_deref(),
+                    config_path.as_deref(),
+                    profile,
+                    json,
+                )
+                .await
+            }
+            WorkspaceAction::Detach { workspace: target } => {
+                handle_workspace_detach(target.as_deref(), workspace, json).await
             }
         },
-        Commands::Run { command } => match handle_run(&command) {
+        Commands::Run { command } => match handle_run(&command, workspace) {
             Ok(exit_code) => std::process::exit(exit_code),
             Err(e) => {
                 if json {
@@ -47,32 +68,67 @@ async fn main() {
                 std::process::exit(1);
             }
         },
-        Commands::Status => handle_status(json).await,
+        Commands::Status => handle_status(workspace, json).await,
         Commands::List => handle_list(json).await,
-        Commands::Cleanup { force } => handle_cleanup(force, json).await,
+        Commands::Cleanup { force, all_repos } => handle_cleanup(force, all_repos, json).await,
+        Commands::Gc { merged, force } => handle_gc(merged, force, json).await,
+        Commands::Undo => handle_undo(config_path.as_deref(), profile, json).await,
+        Commands::Sync { force } => handle_sync(force, json).await,
+        Commands::Init {
+            from_compose,
+            force,
+        } => handle_init(config_path.as_deref(), from_compose.as_deref(), force, json),
+        Commands::Config { action } => match action {
+            ConfigAction::Validate => {
+                match handle_config_validate(config_path.as_deref(), profile, json) {
+                    Ok(exit_code) => std::process::exit(exit_code),
+                    Err(e) => Err(e),
+                }
+            }
+            ConfigAction::Show { resolved } => {
+                handle_config_show(resolved, config_path.as_deref(), profile, json)
+            }
+            ConfigAction::Schema { file } => handle_config_schema(file.as_deref(), json),
+        },
+        Commands::Export { action } => match action {
+            ExportAction::Compose { file } => handle_export_compose(
+                file.as_deref(),
+                config_path.as_deref(),
+                profile,
+                workspace,
+                json,
+            ),
+        },
+        Commands::State { action } => match action {
+            StateAction::Repair { dry_run } => handle_state_repair(dry_run, json).await,
+            StateAction::Migrate { dry_run } => handle_state_migrate(dry_run, json),
+        },
         Commands::Logs {
             service,
             tail,
             follow: _,
-        } => handle_logs(&service, tail, json).await,
-        Commands::Restart { service } => handle_restart(&service, json).await,
-        Commands::Stop { service } => handle_stop(&service, json).await,
-        Commands::Start { service } => handle_start(&service, json).await,
+        } => handle_logs(&service, tail, workspace, json).await,
+        Commands::Restart { service } => handle_restart(&service, workspace, json).await,
+        Commands::Stop { service } => handle_stop(&service, workspace, json).await,
+        Commands::Start { service } => handle_start(&service, workspace, json).await,
         Commands::ContainerExec { service, command } => {
-            handle_container_run(&service, &command, json).await
+            handle_container_run(&service, &command, workspace, json).await
         }
         Commands::Completion { shell, dir } => {
             let dir_path = dir.as_deref().map(Path::new);
             generate_completion(&shell, dir_path)
         }
+        Commands::Jj(args) if json => match handle_jj_json(args, workspace) {
+            Ok(exit_code) => std::process::exit(exit_code),
+            Err(e) => Err(e),
+        },
         Commands::Jj(args) => {
-            let cmd = JjCommand::new(args);
-            match cmd.execute() {
-                Ok(output) => {
-                    print!("{}", output.stdout);
-                    eprint!("{}", output.stderr);
-                    Ok(())
-                }
+            let cmd = match workspace {
+                Some(name) => workspace_path(name).map(|path| JjCommand::new(args).in_dir(&path)),
+                None => Ok(JjCommand::new(args)),
+            };
+            match cmd.and_then(|cmd| cmd.run_inherited()) {
+                Ok(exit_code) => std::process::exit(exit_code),
                 Err(e) => Err(e),
             }
         }
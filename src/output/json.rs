@@ -1,6 +1,9 @@
+use crate::config::Diagnostic;
 use crate::error::AetherError;
+use crate::jj::{DiffStat, FileChange, JjCommit, JjOperation, JjWorkspace};
+use crate::provisioner::WorkspaceState;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AjjOutput {
@@ -18,6 +21,8 @@ pub struct WorkspaceInfo {
     pub root: String,
     pub backend: String,
     pub namespace: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change_id: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub resources: Vec<ResourceDetail>,
 }
@@ -42,7 +47,50 @@ pub struct StatusOutput {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub resources: Vec<ContainerStatus>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_copy: Option<JjCommit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub jj_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drift: Option<DriftReport>,
+}
+
+/// Envelope for jj passthrough commands in JSON mode.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JjPassthroughOutput {
+    pub status: String,
+    /// Arguments passed to jj, as given by the user
+    pub command: Vec<String>,
+    pub exit_code: i32,
+    /// Structured result for commands ajj understands
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<JjData>,
+    /// jj's own output, kept when there is no structured result
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub stdout: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub stderr: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JjData {
+    Log {
+        commits: Vec<JjCommit>,
+    },
+    Status {
+        working_copy: JjCommit,
+        changes: Vec<FileChange>,
+    },
+    DiffStat {
+        #[serde(flatten)]
+        stat: DiffStat,
+    },
+    OpLog {
+        operations: Vec<JjOperation>,
+    },
+    WorkspaceList {
+        workspaces: Vec<JjWorkspace>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,10 +104,194 @@ pub struct ContainerStatus {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CleanupOutput {
     pub status: String,
+    /// Orphaned resources of every kind
     pub orphaned_count: usize,
+    /// Names of every resource removed, across kinds
+    pub removed: Vec<String>,
+    pub containers: OrphanReport,
+    pub networks: OrphanReport,
+    pub volumes: OrphanReport,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stale_workspaces: Vec<StaleWorkspaceReport>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OrphanReport {
+    pub orphaned: Vec<String>,
     pub removed: Vec<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StaleWorkspaceReport {
+    pub name: String,
+    /// `path_missing` or `containers_missing`
+    pub reason: String,
+    /// `drop` or `repair`
+    pub action: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_services: Vec<String>,
+    pub applied: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateRepairOutput {
+    /// `repaired`, `unchanged` or `dry_run`
+    pub status: String,
+    /// Whether the previous registry could be parsed at all
+    pub previous_readable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup: Option<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    /// Containers that could not be attributed to a workspace
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GcOutput {
+    /// `clean`, `dry_run` or `collected`
+    pub status: String,
+    pub candidates: Vec<GcCandidate>,
+    pub forgotten: Vec<String>,
+    /// Workspaces that were skipped and why
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GcCandidate {
+    pub name: String,
+    /// `merged` or `abandoned`
+    pub reason: String,
+    /// Current working-copy change of the workspace
+    pub change_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UndoOutput {
+    pub status: String,
+    /// jj operation that was undone
+    pub operation_id: String,
+    /// `workspace_add` or `workspace_forget` when infrastructure was reversed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reversed: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<WorkspaceInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigValidateOutput {
+    /// `valid` or `invalid`
+    pub status: String,
+    pub file: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigShowOutput {
+    pub file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Files merged, lowest precedence first
+    pub layers: Vec<String>,
+    /// The config as written, before `${VAR}` interpolation
+    pub config: serde_json::Value,
+    /// Dotted key path of each value to the `file:line:column` setting it;
+    /// only with `--resolved`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub origins: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigSchemaOutput {
+    /// Where the schema was written
+    pub file: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportComposeOutput {
+    /// Where the compose file was written
+    pub file: String,
+    pub workspace: String,
+    /// Number of services exported
+    pub services: usize,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InitOutput {
+    pub status: String,
+    /// The config written
+    pub file: String,
+    /// The compose or env example file the services were detected from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub services: Vec<String>,
+    /// What could not be carried over
+    pub warnings: Vec<String>,
+    /// Problems `ajj config validate` reports for the written config
+    pub diagnostics: Vec<Diagnostic>,
+    /// The `.gitignore` the local override file was added to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gitignore: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateMigrateOutput {
+    /// `up_to_date`, `dry_run` or `migrated`
+    pub status: String,
+    pub from_version: String,
+    pub to_version: String,
+    pub migrations: Vec<MigrationInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MigrationInfo {
+    pub from: String,
+    pub to: String,
+    pub description: String,
+}
+
+/// Differences between the VCS's workspaces (`jj workspace list` or
+/// `git worktree list`) and the state registry.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DriftReport {
+    /// Registered with infrastructure, but no longer a workspace of the
+    /// repository (named for jj; also used for git worktrees)
+    pub unknown_to_jj: Vec<String>,
+    /// Workspaces without Aether infrastructure
+    pub without_infra: Vec<String>,
+    /// Registered workspaces whose directory was deleted
+    #[serde(default)]
+    pub missing_directory: Vec<String>,
+}
+
+impl DriftReport {
+    pub fn is_empty(&self) -> bool {
+        self.unknown_to_jj.is_empty()
+            && self.without_infra.is_empty()
+            && self.missing_directory.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListOutput {
+    pub workspaces: Vec<WorkspaceState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drift: Option<DriftReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncOutput {
+    /// `in_sync`, `dry_run` or `synced`
+    pub status: String,
+    pub drift: DriftReport,
+    pub deprovisioned: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorInfo {
     pub code: String,
@@ -73,6 +305,10 @@ impl From<AetherError> for ErrorInfo {
                 code: format!("JJ_FAILED_{}", exit_code),
                 message,
             },
+            AetherError::Git { message, exit_code } => ErrorInfo {
+                code: format!("GIT_FAILED_{}", exit_code),
+                message,
+            },
             AetherError::Config(msg) => ErrorInfo {
                 code: "CONFIG_ERROR".to_string(),
                 message: msg,
@@ -133,6 +369,7 @@ mod tests {
                 root: "/tmp/feature-x".to_string(),
                 backend: "docker".to_string(),
                 namespace: "aether-feature-x".to_string(),
+                change_id: Some("kkmpptxzrspx".to_string()),
                 resources: vec![],
             }),
             errors: vec![],
@@ -140,6 +377,32 @@ mod tests {
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("\"status\":\"ready\""));
         assert!(json.contains("\"operation\":\"workspace_add\""));
+        assert!(json.contains("\"change_id\":\"kkmpptxzrspx\""));
+    }
+
+    #[test]
+    fn test_cleanup_output_serialization() {
+        let output = CleanupOutput {
+            status: "dry_run".to_string(),
+            orphaned_count: 1,
+            removed: vec![],
+            containers: OrphanReport::default(),
+            networks: OrphanReport {
+                orphaned: vec!["aether-abc-old-network".to_string()],
+                removed: vec![],
+            },
+            volumes: OrphanReport::default(),
+            stale_workspaces: vec![StaleWorkspaceReport {
+                name: "feature-x".to_string(),
+                reason: "path_missing".to_string(),
+                action: "drop".to_string(),
+                missing_services: vec![],
+                applied: false,
+            }],
+        };
+        let json = serde_json::to_string(&output).unwrap();
+        assert!(json.contains("\"networks\":{\"orphaned\":[\"aether-abc-old-network\"]"));
+        assert!(json.contains("\"reason\":\"path_missing\""));
     }
 
     #[test]
@@ -155,10 +418,41 @@ mod tests {
                 status: "running".to_string(),
                 port_mappings: HashMap::from([(5432, 32891)]),
             }],
+            working_copy: Some(JjCommit {
+                change_id: "kkmpptxzrspx".to_string(),
+                commit_id: "3d4e5f60a1b2".to_string(),
+                description: "fix: login bug".to_string(),
+                bookmarks: vec!["feature-x".to_string()],
+                conflict: false,
+                empty: false,
+            }),
             jj_status: None,
+            drift: None,
         };
         let json = serde_json::to_string_pretty(&output).unwrap();
         assert!(json.contains("postgres"));
         assert!(json.contains("32891"));
+        assert!(json.contains("\"conflict\": false"));
+    }
+
+    #[test]
+    fn test_jj_passthrough_output_serialization() {
+        let output = JjPassthroughOutput {
+            status: "ok".to_string(),
+            command: vec!["diff".to_string(), "--stat".to_string()],
+            exit_code: 0,
+            data: Some(JjData::DiffStat {
+                stat: DiffStat {
+                    files_changed: 1,
+                    ..DiffStat::default()
+                },
+            }),
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+        let json = serde_json::to_string(&output).unwrap();
+        assert!(json.contains("\"kind\":\"diff_stat\""));
+        assert!(json.contains("\"files_changed\":1"));
+        assert!(!json.contains("stdout"));
     }
 }
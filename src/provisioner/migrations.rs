@@ -0,0 +1,191 @@
+use super::namespace::{is_legacy_namespace, workspace_namespace};
+use crate::error::{AetherError, Result};
+use serde_json::Value;
+
+/// Schema version written by this build of ajj.
+pub const STATE_VERSION: &str = "1.2";
+
+/// Version assumed for registries that predate the `version` field.
+const INITIAL_VERSION: &str = "1.0";
+
+/// Facts about the repository a migration may need.
+pub struct MigrationContext<'a> {
+    pub repo_hash: &'a str,
+}
+
+/// One step in the registry schema history, applied to the raw JSON so older
+/// layouts never have to deserialize into the current types.
+pub struct Migration {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub description: &'static str,
+    apply: fn(&mut Value, &MigrationContext) -> Result<()>,
+}
+
+/// Ordered migrations; each `to` is the next one's `from`.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: "1.0",
+        to: "1.1",
+        description: "Scope namespaces to the repository hash",
+        apply: scope_namespaces,
+    },
+    Migration {
+        from: "1.1",
+        to: "1.2",
+        description: "Add the jj operation journal used by `ajj undo`",
+        apply: add_operation_journal,
+    },
+];
+
+/// Containers are found by their namespace label, which cannot change after
+/// creation, so only entries without resources are moved to the new scheme.
+fn scope_namespaces(registry: &mut Value, ctx: &MigrationContext) -> Result<()> {
+    let Some(workspaces) = registry
+        .get_mut("workspaces")
+        .and_then(Value::as_object_mut)
+    else {
+        return Ok(());
+    };
+
+    for (key, workspace) in workspaces.iter_mut() {
+        let has_resources = workspace
+            .get("resources")
+            .and_then(Value::as_array)
+            .is_some_and(|r| !r.is_empty());
+        let legacy = workspace
+            .get("namespace")
+            .and_then(Value::as_str)
+            .is_some_and(|ns| is_legacy_namespace(ns, ctx.repo_hash));
+
+        if legacy && !has_resources {
+            let name = workspace
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or(key)
+                .to_string();
+            workspace["namespace"] = Value::String(workspace_namespace(ctx.repo_hash, &name));
+        }
+    }
+
+    Ok(())
+}
+
+fn add_operation_journal(registry: &mut Value, _ctx: &MigrationContext) -> Result<()> {
+    if let Some(registry) = registry.as_object_mut() {
+        registry
+            .entry("operations")
+            .or_insert_with(|| Value::Array(vec![]));
+    }
+    Ok(())
+}
+
+fn parse_version(version: &str) -> Result<(u32, u32)> {
+    let invalid = || AetherError::State(format!("Invalid state version: {}", version));
+    let (major, minor) = version.split_once('.').ok_or_else(invalid)?;
+    Ok((
+        major.parse().map_err(|_| invalid())?,
+        minor.parse().map_err(|_| invalid())?,
+    ))
+}
+
+/// Version recorded in a raw registry.
+pub fn registry_version(registry: &Value) -> String {
+    registry
+        .get("version")
+        .and_then(Value::as_str)
+        .unwrap_or(INITIAL_VERSION)
+        .to_string()
+}
+
+/// Migrations needed to bring `version` up to [`STATE_VERSION`], in order.
+///
+/// Fails for registries written by a newer ajj, which this build must not
+/// rewrite, and for versions with no known upgrade path.
+pub fn pending_migrations(version: &str) -> Result<Vec<&'static Migration>> {
+    if parse_version(version)? > parse_version(STATE_VERSION)? {
+        return Err(AetherError::State(format!(
+            "State registry version {} was written by a newer ajj (this build supports {}). \
+             Upgrade ajj to use this repository",
+            version, STATE_VERSION
+        )));
+    }
+
+    let mut pending = Vec::new();
+    let mut current = version;
+    while current != STATE_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.from == current)
+            .ok_or_else(|| {
+                AetherError::State(format!("No migration path from state version {}", current))
+            })?;
+        pending.push(migration);
+        current = migration.to;
+    }
+
+    Ok(pending)
+}
+
+/// Apply every pending migration to `registry` and stamp the new version.
+/// Returns the migrations that ran.
+pub fn migrate(registry: &mut Value, ctx: &MigrationContext) -> Result<Vec<&'static Migration>> {
+    let pending = pending_migrations(&registry_version(registry))?;
+
+    for migration in &pending {
+        (migration.apply)(registry, ctx)?;
+        registry["version"] = Value::String(migration.to.to_string());
+    }
+
+    Ok(pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migration_chain_reaches_current_version() {
+        let pending = pending_migrations(INITIAL_VERSION).unwrap();
+        assert_eq!(pending.last().unwrap().to, STATE_VERSION);
+        assert!(pending_migrations(STATE_VERSION).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_newer_version_is_refused() {
+        let err = pending_migrations("99.0").err().unwrap();
+        assert!(err.to_string().contains("newer ajj"));
+    }
+
+    #[test]
+    fn test_migrate_from_initial_version() {
+        let mut registry = json!({
+            "version": "1.0",
+            "workspaces": {
+                "idle": { "name": "idle", "namespace": "aether-idle", "resources": [] },
+                "busy": {
+                    "name": "busy",
+                    "namespace": "aether-busy",
+                    "resources": [{ "service_name": "postgres" }]
+                }
+            }
+        });
+
+        let ran = migrate(
+            &mut registry,
+            &MigrationContext {
+                repo_hash: "a4f3b2c1",
+            },
+        )
+        .unwrap();
+        assert_eq!(ran.len(), MIGRATIONS.len());
+        assert_eq!(registry["version"], STATE_VERSION);
+        assert_eq!(registry["operations"], json!([]));
+        assert_eq!(
+            registry["workspaces"]["idle"]["namespace"],
+            "aether-a4f3b2c1-idle"
+        );
+        assert_eq!(registry["workspaces"]["busy"]["namespace"], "aether-busy");
+    }
+}
@@ -1,7 +1,12 @@
 pub mod context_injector;
+pub mod migrations;
+pub mod namespace;
 pub mod port_allocator;
+pub mod repo_index;
 pub mod state;
 
 pub use context_injector::*;
+pub use namespace::*;
 pub use port_allocator::*;
+pub use repo_index::*;
 pub use state::*;
@@ -0,0 +1,125 @@
+use std::path::Path;
+
+/// Prefix shared by every namespace, container and network Aether creates.
+pub const NAMESPACE_PREFIX: &str = "aether";
+
+/// Upper bound for a namespace. Containers are named `<namespace>-<service>` and
+/// networks `<namespace>-network`, so this leaves room under the 63 character
+/// DNS label limit for the suffixes.
+pub const NAMESPACE_MAX_LEN: usize = 48;
+
+const REPO_HASH_LEN: usize = 8;
+
+/// FNV-1a, used instead of `DefaultHasher` because the output must be stable
+/// across Rust releases: it ends up in container names and state files.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn short_hash(bytes: &[u8]) -> String {
+    format!("{:016x}", fnv1a(bytes))[..REPO_HASH_LEN].to_string()
+}
+
+/// Stable identifier for a repository, derived from its root path.
+pub fn repo_hash(repo_root: &Path) -> String {
+    let root = repo_root
+        .canonicalize()
+        .unwrap_or_else(|_| repo_root.to_path_buf());
+    short_hash(root.to_string_lossy().as_bytes())
+}
+
+/// Reduce a workspace name to characters Docker and DNS both accept:
+/// lowercase alphanumerics separated by single dashes.
+pub fn sanitize_name(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            sanitized.push(c.to_ascii_lowercase());
+        } else if !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+
+    let sanitized = sanitized.trim_matches('-');
+    if sanitized.is_empty() {
+        "ws".to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+/// Build the `aether-<repo_hash>-<workspace>` namespace for a workspace.
+///
+/// Names that would exceed [`NAMESPACE_MAX_LEN`] are truncated and suffixed
+/// with a hash of the original name so distinct workspaces stay distinct.
+pub fn workspace_namespace(repo_hash: &str, workspace_name: &str) -> String {
+    let prefix = format!("{}-{}-", NAMESPACE_PREFIX, repo_hash);
+    let name = sanitize_name(workspace_name);
+    let budget = NAMESPACE_MAX_LEN.saturating_sub(prefix.len());
+
+    if name.len() <= budget {
+        return format!("{}{}", prefix, name);
+    }
+
+    let suffix = short_hash(workspace_name.as_bytes());
+    let keep = budget.saturating_sub(suffix.len() + 1);
+    let truncated = name[..keep].trim_end_matches('-');
+    format!("{}{}-{}", prefix, truncated, suffix)
+}
+
+/// Whether `namespace` was generated by the pre-repo-hash `aether-<name>` scheme.
+pub fn is_legacy_namespace(namespace: &str, repo_hash: &str) -> bool {
+    !namespace.starts_with(&format!("{}-{}-", NAMESPACE_PREFIX, repo_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name("feature-x"), "feature-x");
+        assert_eq!(sanitize_name("Feature_X"), "feature-x");
+        assert_eq!(sanitize_name("fix: login bug!"), "fix-login-bug");
+        assert_eq!(sanitize_name("--"), "ws");
+        assert_eq!(sanitize_name("日本語"), "ws");
+    }
+
+    #[test]
+    fn test_workspace_namespace_format() {
+        let ns = workspace_namespace("a4f3b2c1", "feature-auth");
+        assert_eq!(ns, "aether-a4f3b2c1-feature-auth");
+    }
+
+    #[test]
+    fn test_workspace_namespace_length_limit() {
+        let long = "a".repeat(100);
+        let other = format!("{}b", "a".repeat(100));
+        let ns = workspace_namespace("a4f3b2c1", &long);
+        assert!(ns.len() <= NAMESPACE_MAX_LEN);
+        assert_ne!(ns, workspace_namespace("a4f3b2c1", &other));
+    }
+
+    #[test]
+    fn test_repo_hash_distinguishes_repos() {
+        let a = repo_hash(Path::new("/nonexistent/repo-a"));
+        let b = repo_hash(Path::new("/nonexistent/repo-b"));
+        assert_eq!(a.len(), REPO_HASH_LEN);
+        assert_ne!(a, b);
+        assert_eq!(a, repo_hash(Path::new("/nonexistent/repo-a")));
+    }
+
+    #[test]
+    fn test_is_legacy_namespace() {
+        assert!(is_legacy_namespace("aether-feature-x", "a4f3b2c1"));
+        assert!(!is_legacy_namespace(
+            "aether-a4f3b2c1-feature-x",
+            "a4f3b2c1"
+        ));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// User-level index of every repository that has registered a workspace,
+/// keyed by repo hash. Lets commands look beyond the current repo's state.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RepoIndexFile {
+    repos: BTreeMap<String, PathBuf>,
+}
+
+pub struct RepoIndex {
+    index_file: PathBuf,
+}
+
+impl RepoIndex {
+    pub fn new(index_file: PathBuf) -> Self {
+        Self { index_file }
+    }
+
+    /// `$XDG_CONFIG_HOME/aether/repos.json`, falling back to `~/.config`.
+    pub fn user_default() -> Option<Self> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+        Some(Self::new(config_dir.join("aether/repos.json")))
+    }
+
+    fn load(&self) -> Result<RepoIndexFile> {
+        if !self.index_file.exists() {
+            return Ok(RepoIndexFile::default());
+        }
+
+        let content = std::fs::read_to_string(&self.index_file)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn record(&self, repo_hash: &str, repo_root: &Path) -> Result<()> {
+        let mut index = self.load()?;
+        if index.repos.get(repo_hash).map(PathBuf::as_path) == Some(repo_root) {
+            return Ok(());
+        }
+        index
+            .repos
+            .insert(repo_hash.to_string(), repo_root.to_path_buf());
+
+        std::fs::create_dir_all(self.index_file.parent().unwrap())?;
+        let tmp_path = self.index_file.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(&index)?)?;
+        std::fs::rename(tmp_path, &self.index_file)?;
+        Ok(())
+    }
+
+    /// Known repository roots that still exist on disk.
+    pub fn repos(&self) -> Result<Vec<(String, PathBuf)>> {
+        Ok(self
+            .load()?
+            .repos
+            .into_iter()
+            .filter(|(_, root)| root.is_dir())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_list_repos() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = RepoIndex::new(temp_dir.path().join("repos.json"));
+
+        index.record("aaaa1111", temp_dir.path()).unwrap();
+        index
+            .record("bbbb2222", &temp_dir.path().join("missing"))
+            .unwrap();
+
+        let repos = index.repos().unwrap();
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].0, "aaaa1111");
+    }
+}
@@ -1,3 +1,8 @@
+use super::migrations::{
+    self, migrate, registry_version, Migration, MigrationContext, STATE_VERSION,
+};
+use super::namespace::{repo_hash, workspace_namespace};
+use crate::config::AetherConfig;
 use crate::error::{AetherError, Result};
 use fs2::FileExt;
 use serde::{Deserialize, Serialize};
@@ -9,6 +14,41 @@ use std::path::{Path, PathBuf};
 pub struct WorkspaceRegistry {
     pub version: String,
     pub workspaces: HashMap<String, WorkspaceState>,
+    /// Workspace changes made alongside jj operations, oldest first
+    #[serde(default)]
+    pub operations: Vec<OperationRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    WorkspaceAdd,
+    WorkspaceForget,
+}
+
+impl OperationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::WorkspaceAdd => "workspace_add",
+            Self::WorkspaceForget => "workspace_forget",
+        }
+    }
+}
+
+/// A workspace change ajj made together with a jj operation, so `ajj undo`
+/// can reverse the infrastructure side when that operation is undone.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OperationRecord {
+    /// jj operation id the change was made in
+    pub operation_id: String,
+    pub kind: OperationKind,
+    /// The workspace as it was registered before being forgotten, or as it
+    /// was provisioned by an add
+    pub workspace: WorkspaceState,
+    /// Configuration the workspace was provisioned with, for re-provisioning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<AetherConfig>,
+    pub recorded_at: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,10 +58,13 @@ pub struct WorkspaceState {
     pub namespace: String,
     pub backend_type: String,
     pub created_at: String,
+    /// jj change checked out when the workspace was provisioned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_id: Option<String>,
     pub resources: Vec<ResourceInfo>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ResourceInfo {
     pub service_name: String,
     pub container_id: String,
@@ -29,17 +72,36 @@ pub struct ResourceInfo {
     pub port_mappings: HashMap<u16, u16>,
 }
 
+/// Number of previous registries kept by [`StateManager::backup`].
+pub const STATE_BACKUP_COUNT: usize = 3;
+
+/// Number of operations kept in the journal; older ones can no longer be
+/// undone with their infrastructure.
+pub const OPERATION_JOURNAL_LEN: usize = 50;
+
 pub struct StateManager {
     state_file: PathBuf,
+    repo_hash: String,
 }
 
 impl StateManager {
     pub fn new(repo_root: &Path) -> Self {
         Self {
             state_file: repo_root.join(".aether/state.json"),
+            repo_hash: repo_hash(repo_root),
         }
     }
 
+    /// Stable identifier of the repository this registry belongs to.
+    pub fn repo_hash(&self) -> &str {
+        &self.repo_hash
+    }
+
+    /// Namespace a workspace of this repository should be provisioned under.
+    pub fn namespace_for(&self, workspace_name: &str) -> String {
+        workspace_namespace(&self.repo_hash, workspace_name)
+    }
+
     fn acquire_lock(&self) -> Result<File> {
         let lock_path = self.state_file.with_extension("lock");
         std::fs::create_dir_all(lock_path.parent().unwrap())?;
@@ -54,16 +116,80 @@ impl StateManager {
         Ok(file)
     }
 
-    fn load_registry(&self) -> Result<WorkspaceRegistry> {
+    fn corrupted(&self, e: serde_json::Error) -> AetherError {
+        AetherError::State(format!(
+            "{} is corrupted ({}). Run `ajj state repair` to rebuild it from the backend",
+            self.state_file.display(),
+            e
+        ))
+    }
+
+    /// Read the state file as untyped JSON so it can be migrated before being
+    /// deserialized. `None` when no registry has been written yet.
+    fn read_raw(&self) -> Result<Option<serde_json::Value>> {
         if !self.state_file.exists() {
-            return Ok(WorkspaceRegistry {
-                version: "1.0".to_string(),
-                workspaces: HashMap::new(),
-            });
+            return Ok(None);
         }
 
         let content = std::fs::read_to_string(&self.state_file)?;
-        Ok(serde_json::from_str(&content)?)
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| self.corrupted(e))
+    }
+
+    /// Load the registry, upgrading and persisting it first if it was written
+    /// by an older ajj. Returns the migrations that ran and the backup taken
+    /// before rewriting the file.
+    fn load_and_migrate(
+        &self,
+    ) -> Result<(WorkspaceRegistry, Vec<&'static Migration>, Option<PathBuf>)> {
+        let Some(mut raw) = self.read_raw()? else {
+            let registry = WorkspaceRegistry {
+                version: STATE_VERSION.to_string(),
+                workspaces: HashMap::new(),
+                operations: vec![],
+            };
+            return Ok((registry, vec![], None));
+        };
+
+        let ctx = MigrationContext {
+            repo_hash: &self.repo_hash,
+        };
+        let ran = migrate(&mut raw, &ctx)?;
+        let registry: WorkspaceRegistry =
+            serde_json::from_value(raw).map_err(|e| self.corrupted(e))?;
+
+        let mut backup = None;
+        if !ran.is_empty() {
+            backup = self.rotate_backups()?;
+            self.atomic_write(&registry)?;
+        }
+
+        Ok((registry, ran, backup))
+    }
+
+    fn load_registry(&self) -> Result<WorkspaceRegistry> {
+        Ok(self.load_and_migrate()?.0)
+    }
+
+    /// Current registry version and the migrations needed to bring it up to
+    /// date, without changing anything on disk.
+    pub fn pending_migrations(&self) -> Result<(String, Vec<&'static Migration>)> {
+        let _lock = self.acquire_lock()?;
+        let version = match self.read_raw()? {
+            Some(raw) => registry_version(&raw),
+            None => STATE_VERSION.to_string(),
+        };
+        let pending = migrations::pending_migrations(&version)?;
+        Ok((version, pending))
+    }
+
+    /// Run pending migrations now rather than on the next access. Returns the
+    /// migrations that ran and the backup taken before rewriting the file.
+    pub fn migrate(&self) -> Result<(Vec<&'static Migration>, Option<PathBuf>)> {
+        let _lock = self.acquire_lock()?;
+        let (_, ran, backup) = self.load_and_migrate()?;
+        Ok((ran, backup))
     }
 
     fn atomic_write(&self, registry: &WorkspaceRegistry) -> Result<()> {
@@ -76,6 +202,64 @@ impl StateManager {
         Ok(())
     }
 
+    fn backup_path(&self, generation: usize) -> PathBuf {
+        self.state_file
+            .with_extension(format!("json.bak.{}", generation))
+    }
+
+    /// Copy the current state file to `state.json.bak.1`, shifting older
+    /// backups up and discarding the oldest. Returns the new backup's path,
+    /// or `None` when there is no state file to back up.
+    pub fn backup(&self) -> Result<Option<PathBuf>> {
+        let _lock = self.acquire_lock()?;
+        self.rotate_backups()
+    }
+
+    /// [`StateManager::backup`] for callers already holding the lock.
+    fn rotate_backups(&self) -> Result<Option<PathBuf>> {
+        if !self.state_file.exists() {
+            return Ok(None);
+        }
+
+        for generation in (1..STATE_BACKUP_COUNT).rev() {
+            let from = self.backup_path(generation);
+            if from.exists() {
+                std::fs::rename(&from, self.backup_path(generation + 1))?;
+            }
+        }
+
+        let backup = self.backup_path(1);
+        std::fs::copy(&self.state_file, &backup)?;
+        Ok(Some(backup))
+    }
+
+    /// Overwrite the registry with exactly `workspaces`, without reading the
+    /// current file. Used to recover from a corrupted state file. The old file
+    /// is backed up first, under the same lock; returns the backup's path.
+    pub fn replace_workspaces(&self, workspaces: Vec<WorkspaceState>) -> Result<Option<PathBuf>> {
+        let _lock = self.acquire_lock()?;
+        // A corrupted file may be replaced, one from a newer ajj may not
+        if let Ok(Some(raw)) = self.read_raw() {
+            migrations::pending_migrations(&registry_version(&raw))?;
+        }
+        let backup = self.rotate_backups()?;
+        // Keep the journal when the old registry is still readable
+        let operations = self
+            .load_registry()
+            .map(|registry| registry.operations)
+            .unwrap_or_default();
+        let registry = WorkspaceRegistry {
+            version: STATE_VERSION.to_string(),
+            workspaces: workspaces
+                .into_iter()
+                .map(|ws| (ws.name.clone(), ws))
+                .collect(),
+            operations,
+        };
+        self.atomic_write(&registry)?;
+        Ok(backup)
+    }
+
     pub fn register_workspace(&self, workspace: WorkspaceState) -> Result<()> {
         let _lock = self.acquire_lock()?;
         let mut registry = self.load_registry()?;
@@ -94,6 +278,47 @@ impl StateManager {
         Ok(())
     }
 
+    /// Append `record` to the operation journal, dropping the oldest entries
+    /// beyond [`OPERATION_JOURNAL_LEN`].
+    pub fn record_operation(&self, record: OperationRecord) -> Result<()> {
+        let _lock = self.acquire_lock()?;
+        let mut registry = self.load_registry()?;
+        registry.operations.push(record);
+        let excess = registry
+            .operations
+            .len()
+            .saturating_sub(OPERATION_JOURNAL_LEN);
+        registry.operations.drain(..excess);
+        self.atomic_write(&registry)
+    }
+
+    /// The journaled workspace change made in jj operation `operation_id`.
+    pub fn find_operation(&self, operation_id: &str) -> Result<Option<OperationRecord>> {
+        let _lock = self.acquire_lock()?;
+        let registry = self.load_registry()?;
+        Ok(registry
+            .operations
+            .into_iter()
+            .rev()
+            .find(|op| op.operation_id == operation_id))
+    }
+
+    /// Every journaled workspace change that `ajj undo` can still reverse.
+    pub fn list_operations(&self) -> Result<Vec<OperationRecord>> {
+        let _lock = self.acquire_lock()?;
+        Ok(self.load_registry()?.operations)
+    }
+
+    /// Drop the journal entry for `operation_id` once it has been undone.
+    pub fn remove_operation(&self, operation_id: &str) -> Result<()> {
+        let _lock = self.acquire_lock()?;
+        let mut registry = self.load_registry()?;
+        registry
+            .operations
+            .retain(|op| op.operation_id != operation_id);
+        self.atomic_write(&registry)
+    }
+
     pub fn get_workspace(&self, name: &str) -> Result<Option<WorkspaceState>> {
         let _lock = self.acquire_lock()?;
         let registry = self.load_registry()?;
@@ -105,6 +330,19 @@ impl StateManager {
         let registry = self.load_registry()?;
         Ok(registry.workspaces.values().cloned().collect())
     }
+
+    /// Find the workspace containing `path`: the registered workspace whose
+    /// path is the longest prefix of it, so nested workspaces resolve to the
+    /// innermost one.
+    pub fn find_workspace_by_path(&self, path: &Path) -> Result<Option<WorkspaceState>> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+        Ok(self
+            .list_workspaces()?
+            .into_iter()
+            .filter(|ws| path.starts_with(&ws.path))
+            .max_by_key(|ws| Path::new(&ws.path).components().count()))
+    }
 }
 
 #[cfg(test)]
@@ -112,6 +350,18 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    fn test_workspace(name: &str) -> WorkspaceState {
+        WorkspaceState {
+            name: name.to_string(),
+            path: format!("/tmp/{}", name),
+            namespace: format!("aether-{}", name),
+            backend_type: "docker".to_string(),
+            created_at: "2026-01-28T00:00:00Z".to_string(),
+            change_id: None,
+            resources: vec![],
+        }
+    }
+
     #[test]
     fn test_register_and_get_workspace() {
         let temp_dir = TempDir::new().unwrap();
@@ -123,6 +373,7 @@ mod tests {
             namespace: "aether-test".to_string(),
             backend_type: "docker".to_string(),
             created_at: "2026-01-28T00:00:00Z".to_string(),
+            change_id: None,
             resources: vec![],
         };
 
@@ -133,6 +384,157 @@ mod tests {
         assert_eq!(retrieved.unwrap().name, "test-ws");
     }
 
+    #[test]
+    fn test_legacy_registry_is_migrated_on_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = StateManager::new(temp_dir.path());
+        std::fs::create_dir_all(temp_dir.path().join(".aether")).unwrap();
+        std::fs::write(
+            temp_dir.path().join(".aether/state.json"),
+            r#"{
+  "version": "1.0",
+  "workspaces": {
+    "idle": {
+      "name": "idle",
+      "path": "/tmp/idle",
+      "namespace": "aether-idle",
+      "backend_type": "docker",
+      "created_at": "2026-01-28T00:00:00Z",
+      "resources": []
+    }
+  }
+}"#,
+        )
+        .unwrap();
+
+        let (version, pending) = manager.pending_migrations().unwrap();
+        assert_eq!(version, "1.0");
+        assert_eq!(pending.len(), migrations::MIGRATIONS.len());
+
+        let idle = manager.get_workspace("idle").unwrap().unwrap();
+        assert_eq!(idle.namespace, manager.namespace_for("idle"));
+        assert!(temp_dir.path().join(".aether/state.json.bak.1").exists());
+
+        let (version, pending) = manager.pending_migrations().unwrap();
+        assert_eq!(version, STATE_VERSION);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_operation_journal() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = StateManager::new(temp_dir.path());
+        let record = |id: usize| OperationRecord {
+            operation_id: format!("op{}", id),
+            kind: OperationKind::WorkspaceAdd,
+            workspace: test_workspace("ws"),
+            config: None,
+            recorded_at: "2026-01-28T00:00:00Z".to_string(),
+        };
+
+        for id in 0..=OPERATION_JOURNAL_LEN {
+            manager.record_operation(record(id)).unwrap();
+        }
+        // The oldest entry fell off the end of the journal
+        assert!(manager.find_operation("op0").unwrap().is_none());
+        let found = manager.find_operation("op1").unwrap().unwrap();
+        assert_eq!(found.kind, OperationKind::WorkspaceAdd);
+
+        manager.remove_operation("op1").unwrap();
+        assert!(manager.find_operation("op1").unwrap().is_none());
+
+        // Repair keeps the journal of a readable registry
+        manager.replace_workspaces(vec![]).unwrap();
+        assert!(manager.find_operation("op2").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_newer_registry_is_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = StateManager::new(temp_dir.path());
+        std::fs::create_dir_all(temp_dir.path().join(".aether")).unwrap();
+        std::fs::write(
+            temp_dir.path().join(".aether/state.json"),
+            r#"{"version": "99.0", "workspaces": {}}"#,
+        )
+        .unwrap();
+
+        assert!(manager.list_workspaces().is_err());
+        assert!(manager.register_workspace(test_workspace("ws")).is_err());
+        assert!(manager.replace_workspaces(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_corrupted_state_is_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = StateManager::new(temp_dir.path());
+        std::fs::create_dir_all(temp_dir.path().join(".aether")).unwrap();
+        std::fs::write(temp_dir.path().join(".aether/state.json"), "{ not json").unwrap();
+
+        let err = manager.list_workspaces().unwrap_err();
+        assert!(err.to_string().contains("ajj state repair"));
+
+        // The corrupted file is kept as a backup
+        let backup = manager.replace_workspaces(vec![]).unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(backup).unwrap(), "{ not json");
+        assert!(manager.list_workspaces().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_backup_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = StateManager::new(temp_dir.path());
+        assert!(manager.backup().unwrap().is_none());
+
+        manager.replace_workspaces(vec![]).unwrap();
+        for _ in 0..STATE_BACKUP_COUNT + 2 {
+            assert!(manager.backup().unwrap().is_some());
+        }
+
+        let aether_dir = temp_dir.path().join(".aether");
+        assert!(aether_dir.join("state.json.bak.1").exists());
+        assert!(aether_dir
+            .join(format!("state.json.bak.{}", STATE_BACKUP_COUNT))
+            .exists());
+        assert!(!aether_dir
+            .join(format!("state.json.bak.{}", STATE_BACKUP_COUNT + 1))
+            .exists());
+    }
+
+    #[test]
+    fn test_find_workspace_by_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = StateManager::new(temp_dir.path());
+        let root = temp_dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("outer/inner/src")).unwrap();
+
+        for (name, path) in [
+            ("outer", root.join("outer")),
+            ("inner", root.join("outer/inner")),
+        ] {
+            manager
+                .register_workspace(WorkspaceState {
+                    path: path.to_string_lossy().to_string(),
+                    ..test_workspace(name)
+                })
+                .unwrap();
+        }
+
+        let found = manager
+            .find_workspace_by_path(&root.join("outer/inner/src"))
+            .unwrap();
+        assert_eq!(found.unwrap().name, "inner");
+
+        let found = manager.find_workspace_by_path(&root.join("outer")).unwrap();
+        assert_eq!(found.unwrap().name, "outer");
+
+        // A sibling sharing a name prefix is not inside the workspace
+        let found = manager
+            .find_workspace_by_path(&root.join("outer-other"))
+            .unwrap();
+        assert!(found.is_none());
+    }
+
     #[test]
     fn test_list_workspaces() {
         let temp_dir = TempDir::new().unwrap();
@@ -1,25 +1,145 @@
 use crate::error::{AetherError, Result};
 use std::path::{Path, PathBuf};
 
-/// Walk up from `start` until a `.jj` directory is found, returning the repo root.
-pub fn find_repo_root(start: &Path) -> Result<PathBuf> {
+/// Version control system a checkout is managed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VcsKind {
+    Jj,
+    Git,
+}
+
+impl VcsKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Jj => "jj",
+            Self::Git => "git",
+        }
+    }
+}
+
+/// VCS whose checkout is rooted at `dir`, if any. Colocated repositories
+/// have both `.jj` and `.git`; jj owns their workspaces. In linked git
+/// worktrees `.git` is a file rather than a directory.
+fn vcs_at(dir: &Path) -> Option<VcsKind> {
+    if dir.join(".jj").is_dir() {
+        Some(VcsKind::Jj)
+    } else if dir.join(".git").exists() {
+        Some(VcsKind::Git)
+    } else {
+        None
+    }
+}
+
+/// Walk up from `start` until a `.jj` directory or `.git` entry is found,
+/// returning the root of the jj workspace or git worktree containing `start`.
+pub fn find_workspace_root(start: &Path) -> Result<PathBuf> {
     let mut current = start.canonicalize()?;
 
     loop {
-        if current.join(".jj").is_dir() {
+        if vcs_at(&current).is_some() {
             return Ok(current);
         }
 
         current = current
             .parent()
-            .ok_or_else(|| AetherError::Config("Not in a jj repository".into()))?
+            .ok_or_else(|| AetherError::Config("Not in a jj or git repository".into()))?
             .to_path_buf();
     }
 }
 
+/// VCS of the workspace containing `start`.
+pub fn detect_vcs_kind(start: &Path) -> Result<VcsKind> {
+    let workspace_root = find_workspace_root(start)?;
+    vcs_at(&workspace_root)
+        .ok_or_else(|| AetherError::Config("Not in a jj or git repository".into()))
+}
+
+/// Find the root of the main workspace of the repository containing `start`.
+///
+/// Secondary workspaces created by `jj workspace add` have their own `.jj`
+/// directory whose `repo` entry is a file pointing at the main workspace's
+/// `.jj/repo`, and linked git worktrees have a `.git` file pointing into the
+/// main worktree's `.git/worktrees`, so state stored under the main root is
+/// shared by every workspace of the repository.
+pub fn find_repo_root(start: &Path) -> Result<PathBuf> {
+    let workspace_root = find_workspace_root(start)?;
+    match vcs_at(&workspace_root) {
+        Some(VcsKind::Git) => git_main_root(&workspace_root),
+        _ => jj_main_root(&workspace_root),
+    }
+}
+
+fn jj_main_root(workspace_root: &Path) -> Result<PathBuf> {
+    let jj_dir = workspace_root.join(".jj");
+    let repo_entry = jj_dir.join("repo");
+
+    if !repo_entry.is_file() {
+        return Ok(workspace_root.to_path_buf());
+    }
+
+    let pointer = std::fs::read_to_string(&repo_entry)?;
+    let repo_dir = jj_dir.join(pointer.trim());
+    let repo_dir = repo_dir.canonicalize().map_err(|e| {
+        AetherError::Config(format!(
+            "Workspace points at missing repository {}: {}",
+            repo_dir.display(),
+            e
+        ))
+    })?;
+
+    // <main>/.jj/repo -> <main>
+    repo_dir
+        .parent()
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .ok_or_else(|| {
+            AetherError::Config(format!(
+                "Unexpected jj repository location: {}",
+                repo_dir.display()
+            ))
+        })
+}
+
+fn git_main_root(worktree_root: &Path) -> Result<PathBuf> {
+    let dot_git = worktree_root.join(".git");
+    if dot_git.is_dir() {
+        return Ok(worktree_root.to_path_buf());
+    }
+
+    // Linked worktree: `gitdir: <main>/.git/worktrees/<name>`
+    let content = std::fs::read_to_string(&dot_git)?;
+    let gitdir = content
+        .trim()
+        .strip_prefix("gitdir:")
+        .map(|p| worktree_root.join(p.trim()))
+        .ok_or_else(|| AetherError::Config(format!("Unexpected {}", dot_git.display())))?;
+
+    // `commondir` points from the worktree's git dir back to <main>/.git
+    let common_dir = match std::fs::read_to_string(gitdir.join("commondir")) {
+        Ok(common) => gitdir.join(common.trim()),
+        Err(_) => gitdir.join("../.."),
+    };
+    let common_dir = common_dir.canonicalize().map_err(|e| {
+        AetherError::Config(format!(
+            "Worktree points at missing repository {}: {}",
+            common_dir.display(),
+            e
+        ))
+    })?;
+
+    common_dir.parent().map(Path::to_path_buf).ok_or_else(|| {
+        AetherError::Config(format!(
+            "Unexpected git repository location: {}",
+            common_dir.display()
+        ))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
+    use tempfile::TempDir;
 
     #[test]
     fn test_find_repo_root_not_in_repo() {
@@ -27,4 +147,55 @@ mod tests {
         let result = find_repo_root(Path::new("/tmp"));
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_find_repo_root_from_secondary_workspace() {
+        let temp_dir = TempDir::new().unwrap();
+        let main = temp_dir.path().join("main");
+        let secondary = temp_dir.path().join("feature-x");
+        fs::create_dir_all(main.join(".jj/repo")).unwrap();
+        fs::create_dir_all(secondary.join(".jj")).unwrap();
+        fs::create_dir_all(secondary.join("src")).unwrap();
+        // jj writes the pointer relative to the workspace's .jj directory
+        fs::write(secondary.join(".jj/repo"), "../../main/.jj/repo").unwrap();
+
+        let main = main.canonicalize().unwrap();
+        assert_eq!(find_repo_root(&main).unwrap(), main);
+        assert_eq!(find_repo_root(&secondary.join("src")).unwrap(), main);
+        assert_eq!(
+            find_workspace_root(&secondary.join("src")).unwrap(),
+            secondary.canonicalize().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_find_repo_root_from_git_worktree() {
+        let temp_dir = TempDir::new().unwrap();
+        let main = temp_dir.path().join("main");
+        let linked = temp_dir.path().join("feature-x");
+        fs::create_dir_all(main.join(".git/worktrees/feature-x")).unwrap();
+        fs::write(main.join(".git/worktrees/feature-x/commondir"), "../..\n").unwrap();
+        fs::create_dir_all(linked.join("src")).unwrap();
+        fs::write(
+            linked.join(".git"),
+            format!(
+                "gitdir: {}\n",
+                main.join(".git/worktrees/feature-x").display()
+            ),
+        )
+        .unwrap();
+
+        let main = main.canonicalize().unwrap();
+        assert_eq!(find_repo_root(&main).unwrap(), main);
+        assert_eq!(find_repo_root(&linked.join("src")).unwrap(), main);
+        assert_eq!(detect_vcs_kind(&linked).unwrap(), VcsKind::Git);
+    }
+
+    #[test]
+    fn test_colocated_repo_is_jj() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join(".jj/repo")).unwrap();
+        fs::create_dir_all(temp_dir.path().join(".git")).unwrap();
+        assert_eq!(detect_vcs_kind(temp_dir.path()).unwrap(), VcsKind::Jj);
+    }
 }
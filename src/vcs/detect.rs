@@ -0,0 +1,15 @@
+use super::git::GitVcs;
+use super::jj::JjVcs;
+use super::traits::Vcs;
+use crate::error::Result;
+use crate::repo::{detect_vcs_kind, find_repo_root, VcsKind};
+use std::path::Path;
+
+/// The VCS managing the repository containing `start`.
+pub fn detect_vcs(start: &Path) -> Result<Box<dyn Vcs>> {
+    let repo_root = find_repo_root(start)?;
+    Ok(match detect_vcs_kind(start)? {
+        VcsKind::Jj => Box::new(JjVcs::new(&repo_root)),
+        VcsKind::Git => Box::new(GitVcs::new(&repo_root)),
+    })
+}
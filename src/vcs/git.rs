@@ -0,0 +1,190 @@
+use super::traits::{Vcs, VcsWorkspace};
+use crate::error::{AetherError, Result};
+use crate::repo::VcsKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Git worktrees as workspaces. The main worktree is registered as
+/// `default`, linked worktrees under their directory name, as jj does.
+pub struct GitVcs {
+    repo_root: PathBuf,
+}
+
+impl GitVcs {
+    pub fn new(repo_root: &Path) -> Self {
+        Self {
+            repo_root: repo_root.to_path_buf(),
+        }
+    }
+
+    fn git(&self, dir: Option<&Path>, args: &[&str]) -> Result<String> {
+        let mut command = Command::new("git");
+        if let Some(dir) = dir {
+            command.current_dir(dir);
+        }
+
+        let output = command
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| AetherError::Git {
+                message: format!("Failed to execute git: {}", e),
+                exit_code: -1,
+            })?;
+
+        if !output.status.success() {
+            return Err(AetherError::Git {
+                message: String::from_utf8_lossy(&output.stderr).to_string(),
+                exit_code: output.status.code().unwrap_or(-1),
+            });
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+/// Arguments for `git worktree add`. Like `jj workspace add`, the new
+/// worktree gets its own line of work: a branch named after the directory,
+/// started from `revision` when given.
+fn worktree_add_args(destination: &str, revision: Option<&str>) -> Vec<String> {
+    let mut args = vec!["worktree".to_string(), "add".to_string()];
+    if let Some(rev) = revision {
+        let branch = Path::new(destination)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(destination);
+        args.push("-b".to_string());
+        args.push(branch.to_string());
+        args.push(destination.to_string());
+        args.push(rev.to_string());
+    } else {
+        args.push(destination.to_string());
+    }
+    args
+}
+
+/// Arguments for `git worktree remove`. Without `--force` git refuses to
+/// remove a worktree with modified or untracked files, which keeps work from
+/// being lost; it is only passed when the user asks to discard it.
+fn worktree_remove_args(path: &Path, force: bool) -> Vec<String> {
+    let mut args = vec!["worktree".to_string(), "remove".to_string()];
+    if force {
+        args.push("--force".to_string());
+    }
+    args.push(path.to_string_lossy().to_string());
+    args
+}
+
+/// Parse `git worktree list --porcelain`: blank-line separated records whose
+/// first line is `worktree <path>`, the main worktree first.
+pub fn parse_worktree_list(output: &str) -> Vec<VcsWorkspace> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("worktree "))
+        .enumerate()
+        .map(|(i, path)| {
+            let path = PathBuf::from(path);
+            let name = if i == 0 {
+                "default".to_string()
+            } else {
+                path.file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            };
+            VcsWorkspace {
+                name,
+                path: Some(path),
+            }
+        })
+        .collect()
+}
+
+impl Vcs for GitVcs {
+    fn kind(&self) -> VcsKind {
+        VcsKind::Git
+    }
+
+    fn add_workspace(&self, destination: &str, revision: Option<&str>) -> Result<()> {
+        let args = worktree_add_args(destination, revision);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        self.git(None, &args)?;
+        Ok(())
+    }
+
+    fn forget_workspace(&self, _name: &str, path: &Path, force: bool) -> Result<()> {
+        let args = worktree_remove_args(path, force);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        self.git(Some(&self.repo_root), &args)?;
+        Ok(())
+    }
+
+    fn list_workspaces(&self) -> Result<Vec<VcsWorkspace>> {
+        let output = self.git(Some(&self.repo_root), &["worktree", "list", "--porcelain"])?;
+        Ok(parse_worktree_list(&output))
+    }
+
+    fn show_file(&self, revision: &str, path: &str) -> Result<String> {
+        let object = format!("{}:{}", revision, path);
+        self.git(Some(&self.repo_root), &["show", &object])
+    }
+
+    fn is_tracked(&self, path: &Path) -> bool {
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return false;
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let name = name.to_string_lossy();
+        self.git(Some(dir), &["ls-files", "--error-unmatch", &name])
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worktree_add_args() {
+        assert_eq!(
+            worktree_add_args("../feature-x", None),
+            vec!["worktree", "add", "../feature-x"]
+        );
+        assert_eq!(
+            worktree_add_args("../feature-x", Some("main")),
+            vec!["worktree", "add", "-b", "feature-x", "../feature-x", "main"]
+        );
+    }
+
+    #[test]
+    fn test_worktree_remove_args() {
+        assert_eq!(
+            worktree_remove_args(Path::new("/work/feature-x"), false),
+            vec!["worktree", "remove", "/work/feature-x"]
+        );
+        assert_eq!(
+            worktree_remove_args(Path::new("/work/feature-x"), true),
+            vec!["worktree", "remove", "--force", "/work/feature-x"]
+        );
+    }
+
+    #[test]
+    fn test_parse_worktree_list() {
+        let output = "worktree /work/repo\n\
+                      HEAD 230dd059e1b0\n\
+                      branch refs/heads/main\n\
+                      \n\
+                      worktree /work/feature-x\n\
+                      HEAD 3d4e5f60a1b2\n\
+                      branch refs/heads/feature-x\n";
+        let worktrees = parse_worktree_list(output);
+        assert_eq!(worktrees.len(), 2);
+        assert_eq!(worktrees[0].name, "default");
+        assert_eq!(worktrees[1].name, "feature-x");
+        assert_eq!(worktrees[1].path, Some(PathBuf::from("/work/feature-x")));
+    }
+}
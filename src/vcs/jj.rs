@@ -0,0 +1,72 @@
+use super::traits::{Vcs, VcsWorkspace};
+use crate::error::Result;
+use crate::jj::{query, JjCommand};
+use crate::repo::VcsKind;
+use std::path::{Path, PathBuf};
+
+pub struct JjVcs {
+    repo_root: PathBuf,
+}
+
+impl JjVcs {
+    pub fn new(repo_root: &Path) -> Self {
+        Self {
+            repo_root: repo_root.to_path_buf(),
+        }
+    }
+}
+
+impl Vcs for JjVcs {
+    fn kind(&self) -> VcsKind {
+        VcsKind::Jj
+    }
+
+    fn add_workspace(&self, destination: &str, revision: Option<&str>) -> Result<()> {
+        JjCommand::workspace_add(destination, revision).execute()?;
+        Ok(())
+    }
+
+    fn forget_workspace(&self, name: &str, _path: &Path, _force: bool) -> Result<()> {
+        JjCommand::workspace_forget(name).execute()?;
+        Ok(())
+    }
+
+    fn list_workspaces(&self) -> Result<Vec<VcsWorkspace>> {
+        Ok(query::workspaces(&self.repo_root)?
+            .into_iter()
+            .map(|ws| VcsWorkspace {
+                name: ws.name,
+                path: None,
+            })
+            .collect())
+    }
+
+    fn show_file(&self, revision: &str, path: &str) -> Result<String> {
+        let output = JjCommand::file_show(revision, path)
+            .in_dir(&self.repo_root)
+            .execute()?;
+        Ok(output.stdout)
+    }
+
+    fn is_tracked(&self, path: &Path) -> bool {
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return false;
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        // jj tracks every file that is not ignored once it snapshots
+        JjCommand::file_list(&name.to_string_lossy())
+            .in_dir(dir)
+            .execute()
+            .is_ok_and(|output| !output.stdout.trim().is_empty())
+    }
+
+    fn current_operation(&self) -> Option<String> {
+        query::current_operation(Path::new("."))
+            .ok()
+            .map(|op| op.id)
+    }
+}
@@ -0,0 +1,9 @@
+pub mod detect;
+pub mod git;
+pub mod jj;
+pub mod traits;
+
+pub use detect::*;
+pub use git::*;
+pub use jj::*;
+pub use traits::*;
@@ -0,0 +1,38 @@
+use crate::error::Result;
+use crate::repo::VcsKind;
+use std::path::{Path, PathBuf};
+
+/// Workspace operations ajj needs from the version control system: jj
+/// workspaces or git worktrees.
+pub trait Vcs {
+    fn kind(&self) -> VcsKind;
+
+    /// Create a workspace at `destination`, based on `revision` if given.
+    fn add_workspace(&self, destination: &str, revision: Option<&str>) -> Result<()>;
+
+    /// Remove the workspace `name` rooted at `path`. `force` discards
+    /// uncommitted and untracked files in the checkout where the VCS would
+    /// otherwise refuse; pass it only on the user's explicit request.
+    fn forget_workspace(&self, name: &str, path: &Path, force: bool) -> Result<()>;
+
+    fn list_workspaces(&self) -> Result<Vec<VcsWorkspace>>;
+
+    /// Contents of `path`, relative to the repository root, at `revision`.
+    fn show_file(&self, revision: &str, path: &str) -> Result<String>;
+
+    /// Whether the file at `path` is under version control.
+    fn is_tracked(&self, path: &Path) -> bool;
+
+    /// Id of the latest operation, for VCSs with an undoable operation log.
+    fn current_operation(&self) -> Option<String> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VcsWorkspace {
+    /// Name ajj registers the workspace under; `default` for the main checkout
+    pub name: String,
+    /// Root of the checkout, when the VCS reports it
+    pub path: Option<PathBuf>,
+}
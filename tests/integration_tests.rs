@@ -1,4 +1,4 @@
-use aether::backend::{Backend, DockerBackend, ServiceSpec};
+use aether::backend::{Backend, DockerBackend, ResourceOwner, ServiceSpec};
 use std::collections::HashMap;
 
 #[tokio::test]
@@ -25,12 +25,32 @@ async fn test_docker_provision_deprovision() {
             cpu_reservation: None,
             memory_limit: None,
             memory_reservation: None,
+            healthcheck: None,
+            entrypoint: None,
+            working_dir: None,
+            user: None,
+            hostname: None,
+            extra_hosts: vec![],
+            tmpfs: vec![],
+            shm_size: None,
+            ulimits: HashMap::new(),
+            cap_add: vec![],
+            cap_drop: vec![],
+            labels: HashMap::new(),
+            restart: None,
+            stop_grace_period: None,
         },
     );
 
+    let owner = ResourceOwner {
+        repo_id: "integration".to_string(),
+        workspace_name: "test".to_string(),
+        workspace_path: "/tmp/test".to_string(),
+    };
+
     // Provision
     let handles = backend
-        .provision("test-namespace-integration", &services)
+        .provision("test-namespace-integration", &owner, &services)
         .await
         .unwrap();
     assert_eq!(handles.len(), 1);
@@ -101,6 +121,7 @@ fn test_state_management() {
         namespace: "aether-test".to_string(),
         backend_type: "docker".to_string(),
         created_at: "2026-01-28T00:00:00Z".to_string(),
+        change_id: None,
         resources: vec![],
     };
 
@@ -147,6 +168,7 @@ fn test_context_injector() {
             container_id: "abc123".to_string(),
             image: "postgres:15".to_string(),
             port_mappings,
+            values: Default::default(),
         },
     );
 